@@ -23,6 +23,12 @@
 use time::Timespec;
 use rstox::core::*;
 use group::{GroupChat, get_group_index};
+use game::GameContext;
+use questions::QuestionBank;
+use stats::QuestionStatsDB;
+use reports::ReportQueue;
+use submissions::SubmissionQueue;
+use tournament::Tournament;
 use db::*;
 use util::*;
 
@@ -34,9 +40,14 @@ pub const STATUS_MESSAGE: &'static str = "Invite me to a group. !trivia starts a
 pub struct Bot<'a> {
     pub tox:          &'a mut Tox,
     pub groups:       Vec<GroupChat>,
-    pub questions:    Vec<String>,    // Stores all of the trivia questions/answers
+    pub questions:    QuestionBank,   // Stores all of the trivia questions/answers
+    pub words:        Vec<String>,    // Stores the word list for scramble games
     pub last_connect: Timespec,
     pub db:           DataBase,
+    pub qstats:       QuestionStatsDB,
+    pub reports:      ReportQueue,
+    pub submissions:  SubmissionQueue,
+    pub tournament:   Option<Tournament>,   // The tournament being played across our groups, if any
 }
 
 impl<'a> Bot<'a> {
@@ -44,9 +55,14 @@ impl<'a> Bot<'a> {
         Bot {
             tox: tox,
             groups: Vec::new(),
-            questions: Vec::new(),
+            questions: QuestionBank::new(),
+            words: Vec::new(),
             last_connect: Timespec::new(0, 0),
             db: DataBase::new(),
+            qstats: QuestionStatsDB::new(),
+            reports: ReportQueue::new(),
+            submissions: SubmissionQueue::new(),
+            tournament: None,
         }
     }
 
@@ -59,6 +75,13 @@ impl<'a> Bot<'a> {
         };
     }
 
+    pub fn send_friend_message(&mut self, friendnumber: u32, message: &str) {
+        match self.tox.send_friend_message(friendnumber, MessageType::Normal, message) {
+            Ok(_)  => (),
+            Err(e) => println!("Failed to send message to friend {}: {:?}", friendnumber, e),
+        };
+    }
+
     pub fn add_group(&mut self, friendnumber: u32, cookie: &Cookie) {
         match self.tox.join_conference(friendnumber, cookie) {
             Ok(groupnumber)  => {
@@ -89,6 +112,22 @@ impl<'a> Bot<'a> {
         self.save();
     }
 
+    /* Returns the group at index along with the context its game is played in */
+    pub fn group_context(&mut self, index: usize) -> (&mut GroupChat, GameContext<'_>) {
+        let group = &mut self.groups[index];
+        let ctx = GameContext {
+            tox: &mut *self.tox,
+            db: &mut self.db,
+            questions: &self.questions,
+            qstats: &mut self.qstats,
+            words: &self.words,
+            tournament: &mut self.tournament,
+            groupnumber: group.groupnumber,
+        };
+
+        (group, ctx)
+    }
+
     pub fn leave_all_groups(&mut self) {
         for g in &self.groups {
             self.tox.delete_conference(g.groupnumber);
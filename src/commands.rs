@@ -22,8 +22,14 @@
 
 use std::fmt::Write;
 use bot::Bot;
-use group::{get_group_index, get_peer_public_key};
-use check_privilege;
+use group::{get_group_index, get_peer_index, get_peer_public_key, DEFAULT_SKIP_FRACTION};
+use questions::parse_question;
+use trivia::QUESTION_TIME_LIMIT;
+use game::game_mode_names;
+use trivia::parse_trivia_options;
+use math::MATH_CATEGORY;
+use tournament::{Tournament, MAX_TOURNAMENT_GAMES, DEFAULT_TOURNAMENT_GAMES};
+use {check_privilege, check_friend_privilege, reload_questions, is_master_key};
 
 // Maximum number of entries to print for the stats command
 const MAX_LEADERBOARD_ENTRIES: i32 = 10;
@@ -31,51 +37,462 @@ const MAX_LEADERBOARD_ENTRIES: i32 = 10;
 lazy_static! {
     static ref COMMANDS: Vec<Command> = {
         let init = vec![
-            Command::new( "!disable",   cmd_disable ),
-            Command::new( "!enable",    cmd_enable  ),
-            Command::new( "!help",      cmd_help    ),
-            Command::new( "!hint",      cmd_hint    ),
-            Command::new( "!quit",      cmd_quit    ),
-            Command::new( "!score",     cmd_score   ),
-            Command::new( "!source",    cmd_source  ),
-            Command::new( "!stats",     cmd_stats   ),
-            Command::new( "!stop",      cmd_stop    ),
-            Command::new( "!trivia",    cmd_trivia  ),
+            Command::new( "!buzz",          cmd_buzz          ),
+            Command::new( "!categories",    cmd_categories    ),
+            Command::new( "!disable",       cmd_disable       ),
+            Command::new( "!enable",        cmd_enable        ),
+            Command::new( "!help",          cmd_help          ),
+            Command::new( "!hint",          cmd_hint          ),
+            Command::new( "!join",          cmd_join          ),
+            Command::new( "!quit",          cmd_quit          ),
+            Command::new( "!report",        cmd_report        ),
+            Command::new( "!score",         cmd_score         ),
+            Command::new( "!setcategories", cmd_setcategories ),
+            Command::new( "!setskip",       cmd_setskip       ),
+            Command::new( "!skip",          cmd_skip          ),
+            Command::new( "!source",        cmd_source        ),
+            Command::new( "!stats",         cmd_stats         ),
+            Command::new( "!team",          cmd_team          ),
+            Command::new( "!tournament",    cmd_tournament    ),
+            Command::new( "!stop",          cmd_stop          ),
+        ];
+
+        init
+    };
+
+    static ref FRIEND_COMMANDS: Vec<FriendCommand> = {
+        let init = vec![
+            FriendCommand::new( "!approve",     cmd_approve     ),
+            FriendCommand::new( "!dismiss",     cmd_dismiss     ),
+            FriendCommand::new( "!help",        cmd_dm_help     ),
+            FriendCommand::new( "!question",    cmd_question    ),
+            FriendCommand::new( "!reject",      cmd_reject      ),
+            FriendCommand::new( "!reload",      cmd_reload      ),
+            FriendCommand::new( "!reports",     cmd_reports     ),
+            FriendCommand::new( "!retire",      cmd_retire      ),
+            FriendCommand::new( "!setanswer",   cmd_setanswer   ),
+            FriendCommand::new( "!submissions", cmd_submissions ),
+            FriendCommand::new( "!submit",      cmd_submit      ),
         ];
 
         init
     };
 }
 
+/* Commands sent to the bot in a private message */
+struct FriendCommand {
+    name: String,
+    func: fn(bot: &mut Bot, friendnumber: u32, args: &str),
+}
+
+impl FriendCommand {
+    fn new(name: &str, func: fn(bot: &mut Bot, friendnumber: u32, args: &str)) -> FriendCommand {
+        FriendCommand {
+            name: name.to_string(),
+            func: func,
+        }
+    }
+
+    fn do_command(&self, bot: &mut Bot, friendnumber: u32, args: &str) {
+        let func = self.func;
+        func(bot, friendnumber, args);
+    }
+}
+
 struct Command {
     name: String,
-    func: fn(bot: &mut Bot, groupnumber: u32, peernumber: u32),
+    func: fn(bot: &mut Bot, groupnumber: u32, peernumber: u32, args: &str),
 }
 
 impl Command {
-    fn new(name: &str, func: fn(bot: &mut Bot, groupnumber: u32, peernumber: u32)) -> Command {
+    fn new(name: &str, func: fn(bot: &mut Bot, groupnumber: u32, peernumber: u32, args: &str)) -> Command {
         Command {
             name: name.to_string(),
             func: func,
         }
     }
 
-    fn do_command(&self, bot: &mut Bot, groupnumber: u32, peernumber: u32) {
+    fn do_command(&self, bot: &mut Bot, groupnumber: u32, peernumber: u32, args: &str) {
         let func = self.func;
-        func(bot, groupnumber, peernumber);
+        func(bot, groupnumber, peernumber, args);
+    }
+}
+
+/* Splits a command message into the command name and its arguments */
+fn split_command(message: &str) -> (&str, &str)
+{
+    let message = message.trim();
+
+    match message.find(char::is_whitespace) {
+        Some(idx) => (&message[..idx], message[idx..].trim()),
+        None      => (message, ""),
     }
 }
 
-pub fn execute(bot: &mut Bot, groupnumber: u32, peernumber: u32, command: &str)
+pub fn execute(bot: &mut Bot, groupnumber: u32, peernumber: u32, message: &str)
 {
+    let (command, args) = split_command(message);
+
     for c in COMMANDS.iter() {
         if c.name == command {
-            return c.do_command(bot, groupnumber, peernumber);
+            return c.do_command(bot, groupnumber, peernumber, args);
+        }
+    }
+
+    // Every game mode is started with its name, e.g. !trivia
+    start_game(bot, groupnumber, peernumber, &command[1..], args);
+}
+
+pub fn execute_friend(bot: &mut Bot, friendnumber: u32, message: &str)
+{
+    let (command, args) = split_command(message);
+
+    for c in FRIEND_COMMANDS.iter() {
+        if c.name == command {
+            return c.do_command(bot, friendnumber, args);
+        }
+    }
+}
+
+/* Parses a report or submission id from the start of args and returns it along with the rest of args */
+fn parse_id(args: &str) -> Option<(u32, &str)>
+{
+    let (id, rest) = split_command(args);
+    let id = id.trim_start_matches('#').parse::<u32>().ok()?;
+    Some((id, rest))
+}
+
+/* Returns the reported question for report id, messaging the admin if it can't be found */
+fn get_reported_question(bot: &mut Bot, friendnumber: u32, id: u32) -> Option<String>
+{
+    let question = bot.reports.get(id).map(|r| r.question.to_string());
+
+    if question.is_none() {
+        bot.send_friend_message(friendnumber, "No such report.");
+    }
+
+    question
+}
+
+fn cmd_approve(bot: &mut Bot, friendnumber: u32, args: &str)
+{
+    if !check_friend_privilege(bot, friendnumber) {
+        return;
+    }
+
+    let id = match parse_id(args) {
+        Some((id, _)) => id,
+        None => return bot.send_friend_message(friendnumber, "Usage: !approve <id>"),
+    };
+
+    let line = match bot.submissions.get(id) {
+        Some(s) => s.line.to_string(),
+        None    => return bot.send_friend_message(friendnumber, "No such submission."),
+    };
+
+    let question = match parse_question(&line) {
+        Some(question) => question,
+        None => return bot.send_friend_message(friendnumber, "Submission is invalid. Use !reject to remove it."),
+    };
+
+    if bot.questions.find(&question.question).is_some() {
+        return bot.send_friend_message(friendnumber, "That question is already in the question file. \
+                                                      Use !reject to remove it.");
+    }
+
+    if let Err(e) = bot.questions.append(&line) {
+        let mut message = String::new();
+        write!(&mut message, "Failed to add question: {}", e).unwrap();
+        return bot.send_friend_message(friendnumber, &message);
+    }
+
+    bot.submissions.take(id);
+    bot.send_friend_message(friendnumber, "Submission approved and added to the question pool.");
+}
+
+fn cmd_dismiss(bot: &mut Bot, friendnumber: u32, args: &str)
+{
+    if !check_friend_privilege(bot, friendnumber) {
+        return;
+    }
+
+    let id = match parse_id(args) {
+        Some((id, _)) => id,
+        None => return bot.send_friend_message(friendnumber, "Usage: !dismiss <id>"),
+    };
+
+    if bot.reports.dismiss(id) {
+        bot.send_friend_message(friendnumber, "Report dismissed.");
+    } else {
+        bot.send_friend_message(friendnumber, "No such report.");
+    }
+}
+
+fn cmd_dm_help(bot: &mut Bot, friendnumber: u32, _args: &str)
+{
+    if !check_friend_privilege(bot, friendnumber) {
+        bot.send_friend_message(friendnumber, "Commands: !submit question | answer | category\n\
+                                               Invite me to a group and type !help there for game commands. \
+                                               During a private game, send your answers here.");
+        return;
+    }
+
+    bot.send_friend_message(friendnumber, "Admin commands: !reports !question <id> !setanswer <id> <answer> \
+                                           !retire <id> !dismiss <id> !submissions !approve <id> !reject <id> !reload");
+}
+
+fn cmd_question(bot: &mut Bot, friendnumber: u32, args: &str)
+{
+    if !check_friend_privilege(bot, friendnumber) {
+        return;
+    }
+
+    let id = match parse_id(args) {
+        Some((id, _)) => id,
+        None => return bot.send_friend_message(friendnumber, "Usage: !question <id>"),
+    };
+
+    let question = match get_reported_question(bot, friendnumber, id) {
+        Some(question) => question,
+        None           => return,
+    };
+
+    let mut message = String::new();
+
+    match bot.questions.find(&question) {
+        Some(q) => {
+            write!(&mut message, "Q: {}\nA: {}\nCategory: {}", q.question, q.answer, q.category).unwrap();
+
+            if !q.choices.is_empty() {
+                write!(&mut message, "\nChoices: {}", q.choices.join(" | ")).unwrap();
+            }
+
+            if q.retired {
+                write!(&mut message, "\n(retired)").unwrap();
+            }
+        },
+        None => write!(&mut message, "Q: {}\n(no longer in the question file)", question).unwrap(),
+    };
+
+    if let Some(stats) = bot.qstats.get(&question) {
+        write!(&mut message, "\nAsked {} times, answered {} times, skipped {} times", stats.asked, stats.answered,
+               stats.skipped).unwrap();
+
+        if let Some(d) = stats.difficulty(QUESTION_TIME_LIMIT) {
+            write!(&mut message, ", difficulty {:.2}", d).unwrap();
         }
     }
+
+    for r in bot.reports.list().iter().filter(|r| r.question == question) {
+        write!(&mut message, "\n#{} {}: {}", r.id, r.reporter, r.reason).unwrap();
+    }
+
+    bot.send_friend_message(friendnumber, &message);
 }
 
-fn cmd_disable(bot: &mut Bot, groupnumber: u32, peernumber: u32)
+fn cmd_reject(bot: &mut Bot, friendnumber: u32, args: &str)
+{
+    if !check_friend_privilege(bot, friendnumber) {
+        return;
+    }
+
+    let id = match parse_id(args) {
+        Some((id, _)) => id,
+        None => return bot.send_friend_message(friendnumber, "Usage: !reject <id>"),
+    };
+
+    match bot.submissions.take(id) {
+        Some(_) => bot.send_friend_message(friendnumber, "Submission rejected."),
+        None    => bot.send_friend_message(friendnumber, "No such submission."),
+    };
+}
+
+fn cmd_reports(bot: &mut Bot, friendnumber: u32, _args: &str)
+{
+    if !check_friend_privilege(bot, friendnumber) {
+        return;
+    }
+
+    if bot.reports.list().is_empty() {
+        return bot.send_friend_message(friendnumber, "No open reports.");
+    }
+
+    let mut message = String::new();
+    write!(&mut message, "Open reports:").unwrap();
+
+    for r in bot.reports.list() {
+        write!(&mut message, "\n#{} {} ({}): {}", r.id, r.question, r.reporter, r.reason).unwrap();
+    }
+
+    bot.send_friend_message(friendnumber, &message);
+}
+
+fn cmd_retire(bot: &mut Bot, friendnumber: u32, args: &str)
+{
+    if !check_friend_privilege(bot, friendnumber) {
+        return;
+    }
+
+    let id = match parse_id(args) {
+        Some((id, _)) => id,
+        None => return bot.send_friend_message(friendnumber, "Usage: !retire <id>"),
+    };
+
+    let question = match get_reported_question(bot, friendnumber, id) {
+        Some(question) => question,
+        None           => return,
+    };
+
+    let result = bot.questions.edit(&question, |fields| fields.push("retired=yes".to_string()));
+
+    if let Err(e) = result {
+        let mut message = String::new();
+        write!(&mut message, "Failed to retire question: {}", e).unwrap();
+        return bot.send_friend_message(friendnumber, &message);
+    }
+
+    bot.reports.resolve(&question);
+    bot.send_friend_message(friendnumber, "Question retired.");
+}
+
+fn cmd_submissions(bot: &mut Bot, friendnumber: u32, _args: &str)
+{
+    if !check_friend_privilege(bot, friendnumber) {
+        return;
+    }
+
+    if bot.submissions.list().is_empty() {
+        return bot.send_friend_message(friendnumber, "No pending submissions.");
+    }
+
+    let mut message = String::new();
+    write!(&mut message, "Pending submissions:").unwrap();
+
+    for s in bot.submissions.list() {
+        write!(&mut message, "\n#{} ({}): {}", s.id, s.submitter, s.line).unwrap();
+    }
+
+    bot.send_friend_message(friendnumber, &message);
+}
+
+/* Reloads the questions file. Games in progress finish their current question first. */
+fn cmd_reload(bot: &mut Bot, friendnumber: u32, _args: &str)
+{
+    if !check_friend_privilege(bot, friendnumber) {
+        return;
+    }
+
+    match reload_questions(bot) {
+        Ok(n)  => bot.send_friend_message(friendnumber, &format!("Reloaded {} questions.", n)),
+        Err(e) => bot.send_friend_message(friendnumber, &format!("Reload failed: {}", e)),
+    }
+}
+
+/* Adds a question to the submission queue. Usage: !submit question | answer | category */
+fn cmd_submit(bot: &mut Bot, friendnumber: u32, args: &str)
+{
+    let parts: Vec<&str> = args.split('|').map(|p| p.trim()).collect();
+
+    if parts.len() < 2 || parts.len() > 3 || parts[0].is_empty() || parts[1].is_empty() {
+        return bot.send_friend_message(friendnumber, "Usage: !submit question | answer | category");
+    }
+
+    if args.contains('`') {
+        return bot.send_friend_message(friendnumber, "Questions may not contain the ` character.");
+    }
+
+    let mut line = String::new();
+    write!(&mut line, "{}`{}", parts[0], parts[1]).unwrap();
+
+    if parts.len() == 3 && !parts[2].is_empty() {
+        if parts[2].contains(',') {
+            return bot.send_friend_message(friendnumber, "Category names may not contain commas.");
+        }
+
+        write!(&mut line, "`category={}", parts[2].to_lowercase()).unwrap();
+    }
+
+    if parse_question(&line).is_none() {
+        return bot.send_friend_message(friendnumber, "Usage: !submit question | answer | category");
+    }
+
+    if bot.questions.find(parts[0]).is_some() {
+        return bot.send_friend_message(friendnumber, "That question is already in play.");
+    }
+
+    let public_key = match bot.tox.get_friend_public_key(friendnumber) {
+        Some(key) => key.to_string(),
+        None      => return,
+    };
+
+    let name = bot.tox.get_friend_name(friendnumber).unwrap_or("Anonymous".to_string());
+
+    match bot.submissions.add(&line, &public_key, &name) {
+        Ok(id) => {
+            let mut message = String::new();
+            write!(&mut message, "Thanks! Your question is #{} in the review queue.", id).unwrap();
+            bot.send_friend_message(friendnumber, &message);
+        },
+        Err(e) => bot.send_friend_message(friendnumber, &e),
+    };
+}
+
+fn cmd_setanswer(bot: &mut Bot, friendnumber: u32, args: &str)
+{
+    if !check_friend_privilege(bot, friendnumber) {
+        return;
+    }
+
+    let (id, answer) = match parse_id(args) {
+        Some((id, answer)) if !answer.is_empty() => (id, answer.replace('`', "'")),
+        _ => return bot.send_friend_message(friendnumber, "Usage: !setanswer <id> <answer>"),
+    };
+
+    let question = match get_reported_question(bot, friendnumber, id) {
+        Some(question) => question,
+        None           => return,
+    };
+
+    let result = bot.questions.edit(&question, |fields| fields[1] = answer.to_string());
+
+    if let Err(e) = result {
+        let mut message = String::new();
+        write!(&mut message, "Failed to edit question: {}", e).unwrap();
+        return bot.send_friend_message(friendnumber, &message);
+    }
+
+    bot.reports.resolve(&question);
+    bot.send_friend_message(friendnumber, "Answer updated.");
+}
+
+fn cmd_categories(bot: &mut Bot, groupnumber: u32, _peernumber: u32, _args: &str)
+{
+    let index = match get_group_index(bot, groupnumber) {
+        Some(index) => index,
+        None        => return,
+    };
+
+    let mut message = String::new();
+    write!(&mut message, "Categories:").unwrap();
+
+    if bot.questions.is_empty() {
+        write!(&mut message, " no questions loaded,").unwrap();
+    }
+
+    for (name, count) in bot.questions.categories() {
+        write!(&mut message, " {} ({}),", name, count).unwrap();
+    }
+
+    write!(&mut message, " {} (generated)", MATH_CATEGORY).unwrap();
+
+    if let Some(defaults) = bot.groups[index].default_options.get("trivia") {
+        write!(&mut message, "\nDefault for this group: {}", defaults).unwrap();
+    }
+
+    bot.groups[index].send_message(bot.tox, &message);
+}
+
+fn cmd_disable(bot: &mut Bot, groupnumber: u32, peernumber: u32, _args: &str)
 {
     if !check_privilege(bot, groupnumber, peernumber) {
         return;
@@ -86,11 +503,11 @@ fn cmd_disable(bot: &mut Bot, groupnumber: u32, peernumber: u32)
         None        => return,
     };
 
-    bot.groups[index].disable_trivia();
-    bot.groups[index].send_message(bot.tox, "Trivia has been disabled");
+    bot.groups[index].disable_games();
+    bot.groups[index].send_message(bot.tox, "Games have been disabled.");
 }
 
-fn cmd_enable(bot: &mut Bot, groupnumber: u32, peernumber: u32)
+fn cmd_enable(bot: &mut Bot, groupnumber: u32, peernumber: u32, _args: &str)
 {
     if !check_privilege(bot, groupnumber, peernumber) {
         return;
@@ -101,34 +518,269 @@ fn cmd_enable(bot: &mut Bot, groupnumber: u32, peernumber: u32)
         None        => return,
     };
 
-    bot.groups[index].send_message(bot.tox, "Trivia has been enabled.");
-    bot.groups[index].enable_trivia();
+    bot.groups[index].send_message(bot.tox, "Games have been enabled.");
+    bot.groups[index].enable_games();
 }
 
-fn cmd_help(bot: &mut Bot, groupnumber: u32, _peernumber: u32)
+fn cmd_help(bot: &mut Bot, groupnumber: u32, _peernumber: u32, _args: &str)
 {
     let index = match get_group_index(bot, groupnumber) {
         Some(index) => index,
         None        => return,
     };
 
-    bot.groups[index].send_message(bot.tox, "Commands: !trivia !hint !score !stats !source");
+    let mut message = String::new();
+    write!(&mut message, "Commands:").unwrap();
+
+    for name in game_mode_names() {
+        write!(&mut message, " !{}", name).unwrap();
+    }
+
+    write!(&mut message, " !buzz !categories !hint !join !report !score !skip !stats !source !team !tournament").unwrap();
+    bot.groups[index].send_message(bot.tox, &message);
+}
+
+fn cmd_buzz(bot: &mut Bot, groupnumber: u32, peernumber: u32, _args: &str)
+{
+    let index = match get_group_index(bot, groupnumber) {
+        Some(index) => index,
+        None        => return,
+    };
+
+    if bot.groups[index].game.is_none() {
+        return;
+    }
+
+    let public_key = match get_peer_public_key(bot.tox, groupnumber, peernumber) {
+        Some(key) => key,
+        None      => return,
+    };
+
+    let (group, mut ctx) = bot.group_context(index);
+    group.buzz(&mut ctx, &public_key);
+}
+
+/* Usage: !team [create <name> | join <name> | leave] */
+fn cmd_team(bot: &mut Bot, groupnumber: u32, peernumber: u32, args: &str)
+{
+    let index = match get_group_index(bot, groupnumber) {
+        Some(index) => index,
+        None        => return,
+    };
+
+    let public_key = match get_peer_public_key(bot.tox, groupnumber, peernumber) {
+        Some(key) => key,
+        None      => return,
+    };
+
+    let (subcommand, name) = split_command(args);
+    let group = &mut bot.groups[index];
+    let mut message = String::new();
+
+    if subcommand.is_empty() {
+        if group.teams.is_empty() {
+            return group.send_message(bot.tox, "There are no teams. Create one with !team create <name>");
+        }
+
+        write!(&mut message, "Teams:").unwrap();
+
+        for team in group.teams.list() {
+            let members: Vec<String> = team.members.iter()
+                                                   .filter_map(|m| get_peer_index(&group.peers, m))
+                                                   .map(|i| group.peers[i].get_nick())
+                                                   .collect();
+
+            let wins = bot.db.get_team(&team.name).map(|t| t.games_won).unwrap_or(0);
+            write!(&mut message, "\n{} ({} wins): {}", team.name, wins, members.join(", ")).unwrap();
+        }
+
+        return group.send_message(bot.tox, &message);
+    }
+
+    if group.game.is_some() {
+        return group.send_message(bot.tox, "Teams can't be changed during a game.");
+    }
+
+    let nick = match get_peer_index(&group.peers, &public_key) {
+        Some(i) => group.peers[i].get_nick(),
+        None    => return,
+    };
+
+    match subcommand {
+        "create" => match group.teams.create(name, &public_key) {
+            Ok(_)  => write!(&mut message, "{} created team {}", nick, name.trim()).unwrap(),
+            Err(e) => write!(&mut message, "{}", e).unwrap(),
+        },
+        "join" => match group.teams.join(name, &public_key) {
+            Ok(team) => write!(&mut message, "{} joined team {}", nick, team).unwrap(),
+            Err(e)   => write!(&mut message, "{}", e).unwrap(),
+        },
+        "leave" => match group.teams.leave(&public_key) {
+            Some(team) => write!(&mut message, "{} left team {}", nick, team).unwrap(),
+            None       => write!(&mut message, "You're not on a team.").unwrap(),
+        },
+        _ => write!(&mut message, "Usage: !team [create <name> | join <name> | leave]").unwrap(),
+    }
+
+    group.send_message(bot.tox, &message);
+}
+
+fn cmd_skip(bot: &mut Bot, groupnumber: u32, peernumber: u32, _args: &str)
+{
+    let index = match get_group_index(bot, groupnumber) {
+        Some(index) => index,
+        None        => return,
+    };
+
+    let public_key = match get_peer_public_key(bot.tox, groupnumber, peernumber) {
+        Some(key) => key,
+        None      => return,
+    };
+
+    let (group, mut ctx) = bot.group_context(index);
+    group.skip(&mut ctx, &public_key);
+}
+
+fn cmd_join(bot: &mut Bot, groupnumber: u32, peernumber: u32, _args: &str)
+{
+    let index = match get_group_index(bot, groupnumber) {
+        Some(index) => index,
+        None        => return,
+    };
+
+    let public_key = match get_peer_public_key(bot.tox, groupnumber, peernumber) {
+        Some(key) => key,
+        None      => return,
+    };
+
+    let (group, mut ctx) = bot.group_context(index);
+    group.join_lobby(&mut ctx, &public_key);
+}
+
+fn cmd_tournament(bot: &mut Bot, groupnumber: u32, peernumber: u32, args: &str)
+{
+    let index = match get_group_index(bot, groupnumber) {
+        Some(index) => index,
+        None        => return,
+    };
+
+    let public_key = match get_peer_public_key(bot.tox, groupnumber, peernumber) {
+        Some(key) => key,
+        None      => return,
+    };
+
+    let nick = match get_peer_index(&bot.groups[index].peers, &public_key) {
+        Some(i) => bot.groups[index].peers[i].get_nick(),
+        None    => return,
+    };
+
+    let (subcommand, arg) = split_command(args);
+    let mut message = String::new();
+
+    // Tournaments span every group, so owning this one isn't enough. Only the organizer and
+    // master keys may run them.
+    let privileged = match subcommand {
+        "create" | "start" | "cancel" => {
+            bot.tournament.as_ref().map_or(false, |t| t.organizer == public_key) || is_master_key(&public_key)
+        },
+        _ => false,
+    };
+
+    match subcommand {
+        "" => match bot.tournament {
+            Some(ref t) => write!(&mut message, "{}", t.standings()).unwrap(),
+            None => {
+                write!(&mut message, "No tournament is running. Start one with !tournament create [games]").unwrap();
+
+                for (i, c) in bot.db.get_champions().iter().enumerate() {
+                    if i as i32 >= MAX_LEADERBOARD_ENTRIES {
+                        break;
+                    }
+
+                    if i == 0 {
+                        write!(&mut message, "\nChampions:").unwrap();
+                    }
+
+                    write!(&mut message, "\n{}: {} titles", c.nick, c.titles).unwrap();
+                }
+            },
+        },
+        "create" => {
+            if !privileged {
+                return;
+            }
+
+            if bot.tournament.is_some() {
+                return bot.groups[index].send_message(bot.tox, "A tournament is already running.");
+            }
+
+            let games = match arg {
+                "" => DEFAULT_TOURNAMENT_GAMES,
+                _  => match arg.parse::<u32>() {
+                    Ok(n) if n > 0 && n <= MAX_TOURNAMENT_GAMES => n,
+                    _ => {
+                        write!(&mut message, "A tournament can last 1 to {} games.", MAX_TOURNAMENT_GAMES).unwrap();
+                        return bot.groups[index].send_message(bot.tox, &message);
+                    },
+                },
+            };
+
+            bot.tournament = Some(Tournament::new(&public_key, games));
+            write!(&mut message, "{} created a tournament of {} games. Type !tournament join to register.",
+                   nick, games).unwrap();
+        },
+        "join" | "leave" | "start" => {
+            let tournament = match bot.tournament {
+                Some(ref mut t) => t,
+                None => return bot.groups[index].send_message(bot.tox, "No tournament is running."),
+            };
+
+            let result = match subcommand {
+                "join"  => tournament.register(&public_key, &nick).map(|_| format!("{} registered for the tournament.", nick)),
+                "leave" => tournament.unregister(&public_key).map(|_| format!("{} left the tournament.", nick)),
+                _ if !privileged => return,
+                _ => tournament.start().map(|_| {
+                    format!("The tournament has begun! The next {} games with a registered player scoring count \
+                             towards the standings.", tournament.games)
+                }),
+            };
+
+            match result {
+                Ok(msg) => write!(&mut message, "{}", msg).unwrap(),
+                Err(e)  => write!(&mut message, "{}", e).unwrap(),
+            }
+        },
+        "cancel" => {
+            if !privileged || bot.tournament.take().is_none() {
+                return;
+            }
+
+            write!(&mut message, "The tournament has been cancelled.").unwrap();
+        },
+        _ => write!(&mut message, "Usage: !tournament [create [games] | join | leave | start | cancel]").unwrap(),
+    }
+
+    bot.groups[index].send_message(bot.tox, &message);
 }
 
-fn cmd_hint(bot: &mut Bot, groupnumber: u32, _peernumber: u32)
+fn cmd_hint(bot: &mut Bot, groupnumber: u32, _peernumber: u32, _args: &str)
 {
     let index = match get_group_index(bot, groupnumber) {
         Some(index) => index,
         None        => return,
     };
 
-    let hint = bot.groups[index].trivia.get_hint();
+    let hint = match bot.groups[index].game {
+        Some(ref mut game) => game.hint(),
+        None               => "Cram it".to_string(),
+    };
+
     let mut message = String::new();
     write!(&mut message, "Hint: {}", hint).unwrap();
     bot.groups[index].send_message(bot.tox, &message);
 }
 
-fn cmd_quit(bot: &mut Bot, groupnumber: u32, peernumber: u32)
+fn cmd_quit(bot: &mut Bot, groupnumber: u32, peernumber: u32, _args: &str)
 {
     if !check_privilege(bot, groupnumber, peernumber) {
         return;
@@ -143,7 +795,42 @@ fn cmd_quit(bot: &mut Bot, groupnumber: u32, peernumber: u32)
     bot.del_group(groupnumber);
 }
 
-fn cmd_score(bot: &mut Bot, groupnumber: u32, peernumber: u32)
+fn cmd_report(bot: &mut Bot, groupnumber: u32, peernumber: u32, args: &str)
+{
+    let index = match get_group_index(bot, groupnumber) {
+        Some(index) => index,
+        None        => return,
+    };
+
+    let public_key = match get_peer_public_key(bot.tox, groupnumber, peernumber) {
+        Some(key) => key,
+        None      => return,
+    };
+
+    let question = match bot.groups[index].game {
+        Some(ref game) => game.last_question(),
+        None           => bot.groups[index].last_question.clone(),
+    };
+
+    let question = match question {
+        Some(question) => question,
+        None => return bot.groups[index].send_message(bot.tox, "There's no question to report."),
+    };
+
+    let nick = match get_peer_index(&bot.groups[index].peers, &public_key) {
+        Some(idx) => bot.groups[index].peers[idx].get_nick(),
+        None      => "Anonymous".to_string(),
+    };
+
+    let reason = if args.is_empty() { "No reason given" } else { args };
+
+    match bot.reports.add(&question, &public_key, &nick, reason) {
+        Some(_) => bot.groups[index].send_message(bot.tox, "Thanks, the question has been reported."),
+        None    => bot.groups[index].send_message(bot.tox, "You've already reported that question."),
+    };
+}
+
+fn cmd_score(bot: &mut Bot, groupnumber: u32, peernumber: u32, _args: &str)
 {
     let public_key = match get_peer_public_key(bot.tox, groupnumber, peernumber) {
         Some(key) => key.to_string(),
@@ -167,7 +854,70 @@ fn cmd_score(bot: &mut Bot, groupnumber: u32, peernumber: u32)
 }
 
 
-fn cmd_source(bot: &mut Bot, groupnumber: u32, _peernumber: u32)
+/* Sets the categories and difficulty used when !trivia is given without any. No arguments clears the default. */
+fn cmd_setcategories(bot: &mut Bot, groupnumber: u32, peernumber: u32, args: &str)
+{
+    if !check_privilege(bot, groupnumber, peernumber) {
+        return;
+    }
+
+    let index = match get_group_index(bot, groupnumber) {
+        Some(index) => index,
+        None        => return,
+    };
+
+    let (categories, difficulty) = match parse_trivia_options(&bot.questions, args) {
+        Ok(options) => options,
+        Err(e) => return bot.groups[index].send_message(bot.tox, &e),
+    };
+
+    if categories.is_empty() && difficulty.is_none() {
+        bot.groups[index].default_options.remove("trivia");
+        bot.groups[index].send_message(bot.tox, "Trivia will use all categories by default.");
+        return;
+    }
+
+    let mut options = categories.join(",");
+
+    if let Some(d) = difficulty {
+        write!(&mut options, " {}", d.name()).unwrap();
+    }
+
+    let mut message = String::new();
+    write!(&mut message, "Trivia will use these options by default: {}", options.trim()).unwrap();
+
+    bot.groups[index].default_options.insert("trivia".to_string(), options.trim().to_string());
+    bot.groups[index].send_message(bot.tox, &message);
+}
+
+/* Sets the percentage of active players who must vote to skip a question. No arguments restores the default. */
+fn cmd_setskip(bot: &mut Bot, groupnumber: u32, peernumber: u32, args: &str)
+{
+    if !check_privilege(bot, groupnumber, peernumber) {
+        return;
+    }
+
+    let index = match get_group_index(bot, groupnumber) {
+        Some(index) => index,
+        None        => return,
+    };
+
+    let percent = match args.trim().trim_end_matches('%') {
+        "" => DEFAULT_SKIP_FRACTION * 100.0,
+        n  => match n.parse::<f64>() {
+            Ok(n) if n > 0.0 && n <= 100.0 => n,
+            _ => return bot.groups[index].send_message(bot.tox, "Usage: !setskip <percent of active players, 1-100>"),
+        },
+    };
+
+    bot.groups[index].skip_fraction = percent / 100.0;
+
+    let mut message = String::new();
+    write!(&mut message, "Questions will be skipped once {}% of active players vote with !skip.", percent).unwrap();
+    bot.groups[index].send_message(bot.tox, &message);
+}
+
+fn cmd_source(bot: &mut Bot, groupnumber: u32, _peernumber: u32, _args: &str)
 {
     let index = match get_group_index(bot, groupnumber) {
         Some(index) => index,
@@ -177,7 +927,7 @@ fn cmd_source(bot: &mut Bot, groupnumber: u32, _peernumber: u32)
     bot.groups[index].send_message(bot.tox, "https://github.com/JFreegman/rustybot/");
 }
 
-fn cmd_stats(bot: &mut Bot, groupnumber: u32, _peernumber: u32)
+fn cmd_stats(bot: &mut Bot, groupnumber: u32, _peernumber: u32, _args: &str)
 {
     let index = match get_group_index(bot, groupnumber) {
         Some(index) => index,
@@ -209,7 +959,7 @@ fn cmd_stats(bot: &mut Bot, groupnumber: u32, _peernumber: u32)
     bot.groups[index].send_message(bot.tox, &message);
 }
 
-fn cmd_stop(bot: &mut Bot, groupnumber: u32, peernumber: u32)
+fn cmd_stop(bot: &mut Bot, groupnumber: u32, peernumber: u32, _args: &str)
 {
     let index = match get_group_index(bot, groupnumber) {
         Some(index) => index,
@@ -217,24 +967,33 @@ fn cmd_stop(bot: &mut Bot, groupnumber: u32, peernumber: u32)
     };
 
     if check_privilege(bot, groupnumber, peernumber) {
-        bot.groups[index].abort_game(bot.tox, true);
+        let (group, mut ctx) = bot.group_context(index);
+        group.abort_game(&mut ctx, true);
         return;
     }
 
-    match get_peer_public_key(bot.tox, groupnumber, peernumber) {
-        Some(pk) => {
-            if pk.to_string() == bot.groups[index].trivia.owner_key {
-                bot.groups[index].abort_game(bot.tox, false);
-            }
-        },
+    let public_key = match get_peer_public_key(bot.tox, groupnumber, peernumber) {
+        Some(pk) => pk,
         None => {
             println!("cmd_stop(): Failed to fetch peer {}'s key in group {}", peernumber, groupnumber);
             return;
         }
     };
+
+    let (group, mut ctx) = bot.group_context(index);
+
+    let is_owner = match (&group.game, &group.lobby) {
+        (&Some(ref game), _)  => game.owner_key() == public_key,
+        (_, &Some(ref lobby)) => lobby.owner_key == public_key,
+        _                     => false,
+    };
+
+    if is_owner {
+        group.abort_game(&mut ctx, false);
+    }
 }
 
-fn cmd_trivia(bot: &mut Bot, groupnumber: u32, peernumber: u32)
+fn start_game(bot: &mut Bot, groupnumber: u32, peernumber: u32, name: &str, args: &str)
 {
     let index = match get_group_index(bot, groupnumber) {
         Some(index) => index,
@@ -244,12 +1003,11 @@ fn cmd_trivia(bot: &mut Bot, groupnumber: u32, peernumber: u32)
     let public_key = match get_peer_public_key(bot.tox, groupnumber, peernumber) {
         Some(pk) => pk,
         None => {
-            println!("cmd_trivia(): Failed to fetch peer {}'s key in group {}", peernumber, groupnumber);
+            println!("start_game(): Failed to fetch peer {}'s key in group {}", peernumber, groupnumber);
             return;
         }
     };
 
-    if bot.groups[index].start_trivia(bot.tox, &public_key) {
-        bot.groups[index].send_message(bot.tox, "Trivia time!");
-    }
+    let (group, mut ctx) = bot.group_context(index);
+    group.start_game(&mut ctx, name, &public_key, args);
 }
@@ -29,6 +29,12 @@ use rstox::core::*;
 
 const DATABASE_PATH: &'static str = "data/scores.db";
 
+// Team records are kept as text lines of the form name`points`games played`games won
+const TEAMS_PATH: &'static str = "data/teams";
+
+// Tournament champions are kept as text lines of the form public key`nick`titles won
+const CHAMPIONS_PATH: &'static str = "data/champions";
+
 // Fixed nick size for serialization
 const DB_NICK_SIZE: usize = 32;
 
@@ -58,13 +64,164 @@ impl DBentry {
     }
 }
 
+/* A team's record across all games. Teams are identified by their name. */
+pub struct TeamEntry {
+    pub name:         String,
+    pub points:       u64,
+    pub games_played: u32,
+    pub games_won:    u32,
+}
+
+/* A player who has won at least one tournament */
+pub struct ChampionEntry {
+    pub nick:   String,   // The nick they last won a tournament with
+    pub titles: u32,      // Number of tournaments won
+}
+
 pub struct DataBase {
-    hashmap: HashMap<String, DBentry>,
+    hashmap:   HashMap<String, DBentry>,
+    teams:     HashMap<String, TeamEntry>,       // Lowercased team name -> record
+    champions: HashMap<String, ChampionEntry>,   // Public key -> tournament titles
 }
 
 impl DataBase {
     pub fn new() -> DataBase {
-        DataBase { hashmap: HashMap::new() }
+        DataBase {
+            hashmap: HashMap::new(),
+            teams: HashMap::new(),
+            champions: HashMap::new(),
+        }
+    }
+
+    pub fn get_team(&self, name: &str) -> Option<&TeamEntry> {
+        self.teams.get(&name.to_lowercase())
+    }
+
+    /* Adds a played game to a team's record */
+    pub fn update_team(&mut self, name: &str, points: u64, won: bool) {
+        let entry = self.teams.entry(name.to_lowercase()).or_insert(TeamEntry {
+            name: name.to_string(),
+            points: 0,
+            games_played: 0,
+            games_won: 0,
+        });
+
+        entry.points += points;
+        entry.games_played += 1;
+
+        if won {
+            entry.games_won += 1;
+        }
+    }
+
+    fn save_teams(&self) {
+        if self.teams.is_empty() {
+            return;
+        }
+
+        let mut data = String::new();
+
+        for t in self.teams.values() {
+            data.push_str(&format!("{}`{}`{}`{}\n", t.name, t.points, t.games_played, t.games_won));
+        }
+
+        match save_data(TEAMS_PATH, &data.into_bytes()) {
+            Ok(_) => (),
+            Err(e) => println!("save_data failed: {}", e),
+        }
+    }
+
+    fn load_teams(&mut self) {
+        let fp = match open_file(TEAMS_PATH, true) {
+            Some(fp) => fp,
+            None     => return,
+        };
+
+        let mut data = String::new();
+
+        if let Err(e) = BufReader::new(&fp).read_to_string(&mut data) {
+            return println!("Failed to read team records: {}", e);
+        }
+
+        for line in data.lines() {
+            let fields: Vec<&str> = line.split('`').collect();
+
+            if fields.len() != 4 {
+                continue;
+            }
+
+            let entry = TeamEntry {
+                name: fields[0].to_string(),
+                points: fields[1].parse().unwrap_or(0),
+                games_played: fields[2].parse().unwrap_or(0),
+                games_won: fields[3].parse().unwrap_or(0),
+            };
+
+            self.teams.insert(fields[0].to_lowercase(), entry);
+        }
+    }
+
+    /* Records a tournament win for key */
+    pub fn add_champion(&mut self, nick: &str, key: &str) {
+        let entry = self.champions.entry(key.to_string()).or_insert(ChampionEntry {
+            nick: String::new(),
+            titles: 0,
+        });
+
+        entry.nick = nick.to_string();
+        entry.titles += 1;
+    }
+
+    /* Returns all tournament champions, most titles first */
+    pub fn get_champions(&self) -> Vec<&ChampionEntry> {
+        let mut list: Vec<&ChampionEntry> = self.champions.values().collect();
+        list.sort_by(|a, b| a.titles.cmp(&b.titles).reverse());
+        list
+    }
+
+    fn save_champions(&self) {
+        if self.champions.is_empty() {
+            return;
+        }
+
+        let mut data = String::new();
+
+        for (key, c) in self.champions.iter() {
+            data.push_str(&format!("{}`{}`{}\n", key, c.nick.replace('`', "'"), c.titles));
+        }
+
+        match save_data(CHAMPIONS_PATH, &data.into_bytes()) {
+            Ok(_) => (),
+            Err(e) => println!("save_data failed: {}", e),
+        }
+    }
+
+    fn load_champions(&mut self) {
+        let fp = match open_file(CHAMPIONS_PATH, true) {
+            Some(fp) => fp,
+            None     => return,
+        };
+
+        let mut data = String::new();
+
+        if let Err(e) = BufReader::new(&fp).read_to_string(&mut data) {
+            return println!("Failed to read tournament champions: {}", e);
+        }
+
+        for line in data.lines() {
+            let fields: Vec<&str> = line.split('`').collect();
+
+            if fields.len() != 3 {
+                continue;
+            }
+
+            let entry = ChampionEntry {
+                nick: fields[1].to_string(),
+                titles: fields[2].parse().unwrap_or(0),
+            };
+
+            self.champions.insert(fields[0].to_string(), entry);
+        }
     }
 
     pub fn set_nick(&mut self, nick: &str, key: &str) {
@@ -109,6 +266,9 @@ impl DataBase {
     }
 
     pub fn save(&self) {
+        self.save_teams();
+        self.save_champions();
+
         if self.hashmap.is_empty() {
             return;
         }
@@ -131,6 +291,9 @@ impl DataBase {
     }
 
     pub fn load(&mut self) {
+        self.load_teams();
+        self.load_champions();
+
         println!("Opening: {}", DATABASE_PATH);
         let fp = match open_file(DATABASE_PATH, true) {
             Some(fp) => fp,
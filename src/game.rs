@@ -0,0 +1,306 @@
+/*  game.rs
+ *
+ *
+ *  Copyright (C) 2015 rustybot All Rights Reserved.
+ *
+ *  This file is part of rustybot.
+ *
+ *  rustybot is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  rustybot is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with rustybot. If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use std::fmt::Write;
+use rstox::core::*;
+use time::Timespec;
+use bot::Bot;
+use db::DataBase;
+use questions::QuestionBank;
+use stats::QuestionStatsDB;
+use tournament::Tournament;
+use group::{Peer, get_group_index, get_peer_public_key, send_group_message, send_friend_message_by_key};
+use trivia::new_trivia;
+use scramble::new_scramble;
+use hangman::new_hangman;
+use math::new_math;
+
+lazy_static! {
+    static ref GAME_MODES: Vec<GameEntry> = {
+        let init = vec![
+            GameEntry::new( "trivia",    new_trivia   ),
+            GameEntry::new( "scramble",  new_scramble ),
+            GameEntry::new( "hangman",   new_hangman  ),
+            GameEntry::new( "math",      new_math     ),
+        ];
+
+        init
+    };
+}
+
+struct GameEntry {
+    name: String,
+    func: fn() -> Box<dyn GameMode>,
+}
+
+impl GameEntry {
+    fn new(name: &str, func: fn() -> Box<dyn GameMode>) -> GameEntry {
+        GameEntry {
+            name: name.to_string(),
+            func: func,
+        }
+    }
+}
+
+/* Everything outside of the group that a game needs access to while it's being played */
+pub struct GameContext<'a> {
+    pub tox:         &'a mut Tox,
+    pub db:          &'a mut DataBase,
+    pub questions:   &'a QuestionBank,
+    pub qstats:      &'a mut QuestionStatsDB,
+    pub words:       &'a Vec<String>,
+    pub tournament:  &'a mut Option<Tournament>,
+    pub groupnumber: u32,
+}
+
+impl<'a> GameContext<'a> {
+    pub fn send_message(&mut self, message: &str) {
+        send_group_message(self.tox, self.groupnumber, message);
+
+        // A tournament tiebreak is followed in every group with a tied player
+        if let Some(ref t) = *self.tournament {
+            if t.tiebreak_host == Some(self.groupnumber) {
+                for &groupnumber in t.tiebreak_groups.iter().flatten() {
+                    send_group_message(self.tox, groupnumber, message);
+                }
+            }
+        }
+    }
+
+    /* Sends a private message to the peer with public_key, if they're our friend */
+    pub fn send_private_message(&mut self, public_key: &str, message: &str) {
+        if !send_friend_message_by_key(self.tox, public_key, message) {
+            println!("Can't send a private message to {}: not a friend", public_key);
+        }
+    }
+}
+
+pub trait GameMode {
+    /* The name the game is started with, e.g. trivia */
+    fn name(&self) -> &str;
+
+    /* The public key of the peer who started the game (enables !stop command) */
+    fn owner_key(&self) -> &str;
+
+    /* Sets up a new game with the options given to the command. Returns false if the game cannot be played. */
+    fn start(&mut self, ctx: &mut GameContext, owner_key: &str, args: &str) -> bool;
+
+    /* Called on every iteration of the main loop. Returns true when the game is over. */
+    fn tick(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>) -> bool;
+
+    /* Called for every group message that isn't a command */
+    fn on_message(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>, public_key: &str, message: &str);
+
+    /*
+     * Returns when the current round began if the game takes private messages from public_key,
+     * or None if it doesn't. A private message goes to the game whose round began most recently.
+     */
+    fn private_round_start(&self, _public_key: &str) -> Option<Timespec> {
+        None
+    }
+
+    /*
+     * Called for a non-command private message from a peer in the group. Returns true if the
+     * game takes answers by private message, whether or not this one was right.
+     */
+    fn on_private_message(&mut self, _ctx: &mut GameContext, _peers: &mut Vec<Peer>, _public_key: &str,
+                          _message: &str) -> bool {
+        false
+    }
+
+    /* Called when a peer uses the !buzz command */
+    fn buzz(&mut self, ctx: &mut GameContext, _peers: &mut Vec<Peer>, _public_key: &str) {
+        ctx.send_message("The buzzer is only used in buzzer games.");
+    }
+
+    /*
+     * Called when a peer uses the !skip command. The question is skipped once fraction of the
+     * game's active players have voted.
+     */
+    fn skip(&mut self, ctx: &mut GameContext, _peers: &mut Vec<Peer>, _public_key: &str, _fraction: f64) {
+        ctx.send_message("This game can't be skipped.");
+    }
+
+    /*
+     * Wakes the game up if it has paused itself. Called when someone uses the game's command while
+     * it's being played. Returns true if it was paused.
+     */
+    fn resume(&mut self, _ctx: &mut GameContext) -> bool {
+        false
+    }
+
+    /* Called when the game ends, whether it finished normally or was aborted */
+    fn on_end(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>);
+
+    fn hint(&mut self) -> String {
+        "No hints for this game".to_string()
+    }
+
+    /* Returns the text of the current or most recently asked question from the question file, for !report */
+    fn last_question(&self) -> Option<String> {
+        None
+    }
+
+    /*
+     * Returns the public keys of the players who won if this game was started to break a tie,
+     * or None for ordinary games
+     */
+    fn tiebreak_winners(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    /* Returns the end of game scoreboard for all peers who scored points */
+    fn scoreboard(&self, peers: &Vec<Peer>) -> String {
+        let mut scorers: Vec<&Peer> = peers.iter().filter(|p| p.round_score > 0).collect();
+        scorers.sort_by(|a, b| a.round_score.cmp(&b.round_score).reverse());
+
+        let mut message = String::new();
+        write!(&mut message, "Scoreboard:\n").unwrap();
+
+        for p in scorers {
+            write!(&mut message, "{}: {}\n", p.get_nick(), p.round_score).unwrap();
+        }
+
+        message
+    }
+}
+
+/* Returns a new instance of the game mode called name, or None if no such game exists */
+pub fn new_game_mode(name: &str) -> Option<Box<dyn GameMode>>
+{
+    for g in GAME_MODES.iter() {
+        if g.name == name {
+            let func = g.func;
+            return Some(func());
+        }
+    }
+
+    None
+}
+
+/* Returns a list of all registered game names */
+pub fn game_mode_names() -> Vec<String>
+{
+    GAME_MODES.iter().map(|g| g.name.to_string()).collect()
+}
+
+/* Passes a non-command group message to the group's game */
+pub fn process_message(bot: &mut Bot, groupnumber: u32, peernumber: u32, message: &str)
+{
+    let index = match get_group_index(bot, groupnumber) {
+        Some(index) => index,
+        None => return println!("get_group_index() failed in process_message() for groupnumber {}", groupnumber),
+    };
+
+    let tiebreak_host = bot.tournament.as_ref().and_then(|t| t.tiebreak_host_for(groupnumber));
+
+    if bot.groups[index].game.is_none() && tiebreak_host.is_none() {
+        return;
+    }
+
+    let public_key = match get_peer_public_key(bot.tox, groupnumber, peernumber) {
+        Some(key) => key,
+        None => return println!("get_peer_public_key() failed in process_message() for groupnumber {}, peernumber {}",
+                                groupnumber, peernumber),
+    };
+
+    {
+        let (group, mut ctx) = bot.group_context(index);
+        group.on_message(&mut ctx, &public_key, message);
+    }
+
+    // Tied tournament players answer the tiebreak from whichever group they're in
+    if let Some(host) = tiebreak_host.and_then(|g| get_group_index(bot, g)) {
+        let (group, mut ctx) = bot.group_context(host);
+        group.on_message(&mut ctx, &public_key, message);
+    }
+}
+
+/*
+ * Once a tournament tiebreak has started, finds the other groups with tied players in them and
+ * tells them about it. From then on they see the tiebreak and the tied players may answer there.
+ */
+fn find_tiebreak_groups(bot: &mut Bot)
+{
+    let (host, players) = match bot.tournament {
+        Some(ref t) if t.tiebreak_host.is_some() && t.tiebreak_groups.is_none() => {
+            (t.tiebreak_host.unwrap(), t.leaders().iter().map(|e| e.public_key.to_string()).collect::<Vec<String>>())
+        },
+        _ => return,
+    };
+
+    let groups: Vec<u32> = bot.groups.iter()
+                                     .filter(|g| g.groupnumber != host)
+                                     .filter(|g| g.peers.iter().any(|p| players.contains(&p.public_key)))
+                                     .map(|g| g.groupnumber)
+                                     .collect();
+
+    let tournament = bot.tournament.as_mut().unwrap();
+    let names: Vec<&str> = players.iter().filter_map(|pk| tournament.get_nick(pk)).collect();
+    let message = format!("The tournament is tied between {}! Sudden death is being played, and the tied players \
+                           may answer here.", names.join(" and "));
+
+    for &groupnumber in &groups {
+        send_group_message(bot.tox, groupnumber, &message);
+    }
+
+    tournament.tiebreak_groups = Some(groups);
+}
+
+/*
+ * Passes a private message to one game the friend is playing. If several of their groups take
+ * private answers it goes to the one whose question is newest, so one answer never scores twice.
+ * Tells the friend if none of them take private answers.
+ */
+pub fn process_private_message(bot: &mut Bot, friendnumber: u32, message: &str)
+{
+    let public_key = match bot.tox.get_friend_public_key(friendnumber) {
+        Some(key) => key.to_string(),
+        None      => return println!("Failed to fetch friend {}'s key", friendnumber),
+    };
+
+    let newest = (0..bot.groups.len()).filter_map(|i| bot.groups[i].private_round_start(&public_key).map(|t| (i, t)))
+                                      .max_by_key(|&(_, t)| t);
+
+    let accepted = match newest {
+        Some((index, _)) => {
+            let (group, mut ctx) = bot.group_context(index);
+            group.on_private_message(&mut ctx, &public_key, message)
+        },
+        None => false,
+    };
+
+    if !accepted {
+        bot.send_friend_message(friendnumber, "None of your groups are playing a game with private answers. \
+                                               Type !help for commands.");
+    }
+}
+
+pub fn do_games(bot: &mut Bot)
+{
+    find_tiebreak_groups(bot);
+
+    for index in 0..bot.groups.len() {
+        let (group, mut ctx) = bot.group_context(index);
+        group.tick(&mut ctx);
+    }
+}
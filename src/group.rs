@@ -20,12 +20,24 @@
  *
  */
 
-use time::get_time;
 use std::fmt::Write;
+use std::collections::HashMap;
 use bot::Bot;
-use trivia::*;
-use db::*;
+use game::{GameMode, GameContext, new_game_mode};
+use teams::Teams;
+use lobby::{Lobby, LOBBY_OPTION, LOBBY_MIN_PLAYERS, LOBBY_COUNTDOWN};
+use trivia::{take_option, PRIVATE_OPTION, SEALED_OPTION, BUZZER_OPTION, WAGER_OPTION, ENDLESS_OPTION};
+use suddendeath::SuddenDeath;
+use tournament::TournamentStage;
 use rstox::core::*;
+use time::Timespec;
+
+// Share of a game's active players who must vote with !skip to skip a question, unless the group sets its own
+pub const DEFAULT_SKIP_FRACTION: f64 = 0.5;
+
+// Game options that choose how a game is played rather than what it asks; they don't replace the group's defaults
+const MODE_OPTIONS: [&'static str; 6] = [LOBBY_OPTION, PRIVATE_OPTION, SEALED_OPTION, BUZZER_OPTION, WAGER_OPTION,
+                                         ENDLESS_OPTION];
 
 pub struct Peer {
     pub nick:            String,
@@ -53,6 +65,16 @@ impl Peer {
         self.rounds_won += 1;
     }
 
+    /* Adds points without counting a round win */
+    pub fn add_points(&mut self, points: u64) {
+        self.round_score += points;
+    }
+
+    /* Takes away up to points from the round score */
+    pub fn remove_points(&mut self, points: u64) {
+        self.round_score = self.round_score.saturating_sub(points);
+    }
+
     pub fn get_round_score(&self) -> u64 {
         self.round_score
     }
@@ -67,157 +89,506 @@ impl Peer {
     }
 }
 
+/* What a sudden death game being played in a group will decide */
+#[derive(Clone, Copy, PartialEq)]
+pub enum Tiebreak {
+    Game,         // The winner of the game that just ended
+    Tournament,   // The tournament champion
+}
+
 pub struct GroupChat {
     pub groupnumber: u32,
-    pub trivia:      Trivia,
+    pub game:        Option<Box<dyn GameMode>>,  // The game currently being played, if any
     pub peers:       Vec<Peer>,
     pub owner_pk:    String,   // Public key of the friend who invited the bot to the group
+    pub disabled:    bool,     // True if games have been disabled
+    pub default_options: HashMap<String, String>,  // Game name -> options used when a game is started without any
+    pub last_question: Option<String>,  // Last question asked by the previous game, for !report
+    pub teams:       Teams,
+    pub tiebreak:    Option<Tiebreak>,  // Set while a sudden death game is being played
+    pub lobby:       Option<Lobby>,     // Sign-ups for the next game, if they're open
+    pub registered:  Vec<String>,       // Players who signed up for the current game. If any, only they can score.
+    pub skip_fraction: f64,             // Share of active players whose !skip votes skip a question
 }
 
 impl GroupChat {
     pub fn new(groupnumber: u32, public_key: String) -> GroupChat {
         GroupChat {
             groupnumber: groupnumber,
-            trivia: Trivia::new(),
+            game: None,
             peers: Vec::new(),
             owner_pk: public_key,
+            disabled: false,
+            default_options: HashMap::new(),
+            last_question: None,
+            teams: Teams::new(),
+            tiebreak: None,
+            lobby: None,
+            registered: Vec::new(),
+            skip_fraction: DEFAULT_SKIP_FRACTION,
         }
     }
 
     pub fn send_message(&self, tox: &mut Tox, message: &str) {
-        match tox.send_conference_message(self.groupnumber, MessageType::Normal, message) {
-            Ok(_)  => (),
-            Err(e) => println!("Failed to send message to group {}: {:?}", self.groupnumber, e),
-        };
+        send_group_message(tox, self.groupnumber, message);
     }
 
-    /* Returns true if game is started */
-    pub fn start_trivia(&mut self, tox: &mut Tox, owner_key: &str) -> bool {
-        if self.trivia.running {
+    /* Returns true if game is started or sign-ups for it are opened */
+    pub fn start_game(&mut self, ctx: &mut GameContext, name: &str, owner_key: &str, args: &str) -> bool {
+        let mut game = match new_game_mode(name) {
+            Some(game) => game,
+            None       => return false,
+        };
+
+        // Using a paused game's own command wakes it up
+        if let Some(ref mut game) = self.game {
+            if game.name() != name || !game.resume(ctx) {
+                ctx.send_message("A game is already in progress.");
+            }
+
+            return false;
+        }
+
+        if self.lobby.is_some() {
+            ctx.send_message("Sign-ups for a game are already open. Type !join to play.");
             return false;
         }
 
-        if self.trivia.disabled {
-            self.send_message(tox, "Trivia is disabled.");
+        if self.disabled {
+            ctx.send_message("Games are disabled.");
+            return false;
+        }
+
+        let args = self.with_default_options(name, args);
+        let (lobby, args) = take_option(&args, LOBBY_OPTION);
+
+        if lobby {
+            let mut message = String::new();
+            write!(&mut message, "Sign-ups for {} are open! Type !join to play. The game starts {} seconds after \
+                                  {} players have joined, and only they can score.", name, LOBBY_COUNTDOWN,
+                                  LOBBY_MIN_PLAYERS).unwrap();
+            ctx.send_message(&message);
+
+            self.lobby = Some(Lobby::new(name, owner_key, &args));
+            return true;
+        }
+
+        if !game.start(ctx, owner_key, &args) {
             return false;
         }
 
-        self.trivia.new_game(owner_key);
+        self.game = Some(game);
         true
     }
 
-    pub fn end_trivia(&mut self, tox: &mut Tox, db: &mut DataBase) {
-        if !self.trivia.running {
-            return;
-        }
+    /*
+     * Returns a game's arguments with the group's default options for it added if none of the
+     * arguments say what to ask, e.g. "private" becomes "private history hard"
+     */
+    fn with_default_options(&self, name: &str, args: &str) -> String {
+        let mut modes = Vec::new();
+        let mut rest = args.to_string();
 
-        self.trivia.reset();
-        let mut winners = Vec::new();
-        let mut winner_pk = String::new();
-        let mut winner_name = String::new();
-        let mut best_score = 0;
+        for option in MODE_OPTIONS.iter() {
+            let (found, remaining) = take_option(&rest, option);
 
-        for p in &self.peers {
-            if p.round_score == 0 {
-                continue;
+            if found {
+                modes.push(option.to_string());
             }
 
-            let pk = p.public_key.to_string();
+            rest = remaining;
+        }
+
+        match self.default_options.get(name) {
+            Some(options) if rest.is_empty() => modes.push(options.to_string()),
+            _ => modes.push(rest),
+        };
+
+        modes.join(" ").trim().to_string()
+    }
 
-            match get_peer_index(&self.peers, &pk) {
-                Some(index) => winners.push(index),
-                None => continue,
-            };
+    /* Signs public_key up for the game in the lobby */
+    pub fn join_lobby(&mut self, ctx: &mut GameContext, public_key: &str) {
+        let nick = match get_peer_index(&self.peers, public_key) {
+            Some(i) => self.peers[i].get_nick(),
+            None    => return,
+        };
 
-            let peername = p.get_nick();
+        let lobby = match self.lobby {
+            Some(ref mut lobby) => lobby,
+            None => return ctx.send_message("There's no game to join. Open sign-ups with lobby, e.g. !trivia lobby"),
+        };
 
-            db.update_score(&peername, &pk, p.round_score, p.rounds_won);
+        let message = match lobby.join(public_key) {
+            Ok(true) => format!("{} joined. {} starts in {} seconds!", nick, lobby.game, LOBBY_COUNTDOWN),
+            Ok(false) if lobby.players.len() < LOBBY_MIN_PLAYERS => {
+                format!("{} joined ({} of {} players needed).", nick, lobby.players.len(), LOBBY_MIN_PLAYERS)
+            },
+            Ok(false) => format!("{} joined ({} players).", nick, lobby.players.len()),
+            Err(e) => e,
+        };
 
-            if p.round_score > best_score && !pk.is_empty() {
-                best_score = p.round_score;
-                winner_pk = pk;
-                winner_name = peername;
-            }
+        ctx.send_message(&message);
+    }
+
+    /* Starts the lobby's game once its countdown has run out, or closes it if too few players joined */
+    fn tick_lobby(&mut self, ctx: &mut GameContext) {
+        let (ready, expired) = match self.lobby {
+            Some(ref lobby) => (lobby.is_ready(), lobby.is_expired()),
+            None            => return,
+        };
+
+        if expired {
+            self.lobby = None;
+            return ctx.send_message("Not enough players joined in time. Sign-ups are closed.");
+        }
+
+        if !ready {
+            return;
         }
 
+        let lobby = self.lobby.take().unwrap();
+
+        let mut game = match new_game_mode(&lobby.game) {
+            Some(game) => game,
+            None       => return,
+        };
+
         let mut message = String::new();
 
-        if winners.is_empty() {
-            write!(&mut message, "Game over.\n").unwrap();
-            self.send_message(tox, &message);
-            return;
+        if self.disabled {
+            write!(&mut message, "Games have been disabled, so {} won't start. Sign-ups are closed.", lobby.game).unwrap();
+            return ctx.send_message(&message);
         }
 
-        write!(&mut message, "Game over. The winner is {}!\nScoreboard:\n", winner_name).unwrap();
+        // The game explains what was wrong with its options
+        if !game.start(ctx, &lobby.owner_key, &lobby.args) {
+            write!(&mut message, "{} couldn't be started, so sign-ups are closed.", lobby.game).unwrap();
+            return ctx.send_message(&message);
+        }
 
-        winners.sort_by(|a, b| self.peers[*a].round_score.cmp(&self.peers[*b].round_score).reverse());
+        self.registered = lobby.players;
+        self.game = Some(game);
+    }
+
+    /* Returns true if public_key may score in the current game */
+    fn may_play(&self, public_key: &str) -> bool {
+        self.registered.is_empty() || self.registered.iter().any(|p| p == public_key)
+    }
 
-        for &index in winners.iter() {
-            let score = self.peers[index].round_score;
-            let peername = self.peers[index].get_nick();
-            write!(&mut message, "{}: {}\n", peername, score).unwrap();
+    pub fn tick(&mut self, ctx: &mut GameContext) {
+        self.tick_lobby(ctx);
+
+        let game_over = match self.game {
+            Some(ref mut game) => game.tick(ctx, &mut self.peers),
+            None               => return,
+        };
 
-            self.peers[index].clear_round();
+        if game_over {
+            self.end_game(ctx);
         }
+    }
 
-        self.send_message(tox, &message);
+    pub fn on_message(&mut self, ctx: &mut GameContext, public_key: &str, message: &str) {
+        if !self.may_play(public_key) {
+            return;
+        }
 
-        db.update_score(&winner_name, &winner_pk, 0, 0);
-        db.save();
+        if let Some(ref mut game) = self.game {
+            game.on_message(ctx, &mut self.peers, public_key, message);
+        }
     }
 
-    pub fn abort_game(&mut self, tox: &mut Tox, privileged: bool) {
-        if !self.trivia.running {
+    pub fn buzz(&mut self, ctx: &mut GameContext, public_key: &str) {
+        if !self.may_play(public_key) {
             return;
         }
 
-        // if we're not privileged but but we own this game of trivia we can only stop
-        // the game if no other peers have a positive score
-        if !privileged {
+        if let Some(ref mut game) = self.game {
+            game.buzz(ctx, &mut self.peers, public_key);
+        }
+    }
+
+    pub fn skip(&mut self, ctx: &mut GameContext, public_key: &str) {
+        if !self.may_play(public_key) {
+            return;
+        }
+
+        if let Some(ref mut game) = self.game {
+            game.skip(ctx, &mut self.peers, public_key, self.skip_fraction);
+        }
+    }
+
+    /* Returns when the current round began if public_key is in this group and its game takes private messages from them */
+    pub fn private_round_start(&self, public_key: &str) -> Option<Timespec> {
+        if get_peer_index(&self.peers, public_key).is_none() || !self.may_play(public_key) {
+            return None;
+        }
+
+        match self.game {
+            Some(ref game) => game.private_round_start(public_key),
+            None           => None,
+        }
+    }
+
+    /* Returns true if the peer with public_key is in this group and its game took the message */
+    pub fn on_private_message(&mut self, ctx: &mut GameContext, public_key: &str, message: &str) -> bool {
+        if get_peer_index(&self.peers, public_key).is_none() || !self.may_play(public_key) {
+            return false;
+        }
+
+        match self.game {
+            Some(ref mut game) => game.on_private_message(ctx, &mut self.peers, public_key, message),
+            None               => false,
+        }
+    }
+
+    pub fn end_game(&mut self, ctx: &mut GameContext) {
+        let mut game = match self.game.take() {
+            Some(game) => game,
+            None       => return,
+        };
+
+        self.last_question = game.last_question();
+        self.registered.clear();
+        game.on_end(ctx, &mut self.peers);
+
+        if let Some(winners) = game.tiebreak_winners() {
+            return self.finish_tiebreak(ctx, &winners);
+        }
+
+        let best_score = self.peers.iter()
+                                   .filter(|p| !p.public_key.is_empty())
+                                   .map(|p| p.round_score)
+                                   .max()
+                                   .unwrap_or(0);
+
+        if best_score == 0 {
             for p in &mut self.peers {
-                if p.round_score > 0 && p.public_key != self.trivia.owner_key {
-                    return;
-                }
+                p.clear_round();
+            }
+
+            return ctx.send_message("Game over.\n");
+        }
+
+        let leaders: Vec<&Peer> = self.peers.iter()
+                                            .filter(|p| p.round_score == best_score && !p.public_key.is_empty())
+                                            .collect();
+
+        let winners: Vec<String> = leaders.iter().map(|p| p.public_key.to_string()).collect();
+        let mut message = String::new();
+
+        if leaders.len() == 1 {
+            write!(&mut message, "Game over. The winner is {}!\n{}", leaders[0].get_nick(), game.scoreboard(&self.peers)).unwrap();
+            return self.finish_game(ctx, &winners, message);
+        }
+
+        // Round scores are kept until sudden death decides who won
+        let names: Vec<String> = leaders.iter().map(|p| p.get_nick()).collect();
+        let contestants: Vec<Peer> = leaders.iter().map(|p| Peer::new(p.public_key.to_string(), p.get_nick(), 0, 0)).collect();
+
+        write!(&mut message, "Game over. It's a tie between {}!\n{}", names.join(" and "), game.scoreboard(&self.peers)).unwrap();
+        ctx.send_message(&message);
+
+        self.start_tiebreak(ctx, Tiebreak::Game, contestants);
+    }
+
+    /*
+     * Records the scores of a finished game in the database, along with a game win for each of
+     * the winners, and announces the results
+     */
+    fn finish_game(&mut self, ctx: &mut GameContext, winners: &[String], mut message: String) {
+        for p in &self.peers {
+            if p.round_score > 0 {
+                ctx.db.update_score(&p.get_nick(), &p.public_key, p.round_score, p.rounds_won);
             }
         }
 
-        self.trivia.reset();
+        for p in &self.peers {
+            if winners.contains(&p.public_key) {
+                ctx.db.update_score(&p.get_nick(), &p.public_key, 0, 0);
+            }
+        }
+
+        if !self.teams.is_empty() {
+            write!(&mut message, "\n{}", self.team_results(ctx)).unwrap();
+        }
+
+        if let Some(ref mut tournament) = *ctx.tournament {
+            if tournament.record_game(&self.peers, winners) {
+                write!(&mut message, "\n{}", tournament.standings()).unwrap();
+            }
+        }
 
         for p in &mut self.peers {
             p.clear_round();
         }
 
-        self.send_message(tox, "Game aborted.");
+        ctx.send_message(&message);
+        ctx.db.save();
+
+        self.finish_tournament(ctx);
     }
 
-    pub fn enable_trivia(&mut self) {
-        self.trivia.disabled = false;
+    /*
+     * Starts a sudden death game between the tied contestants. If it can't be played they
+     * share the win.
+     */
+    fn start_tiebreak(&mut self, ctx: &mut GameContext, tiebreak: Tiebreak, contestants: Vec<Peer>) {
+        let mut game = SuddenDeath::new(contestants);
+
+        self.tiebreak = Some(tiebreak);
+
+        if !game.start(ctx, "", "") {
+            let winners = game.tiebreak_winners().unwrap_or_default();
+            return self.finish_tiebreak(ctx, &winners);
+        }
+
+        self.game = Some(Box::new(game));
     }
 
-    pub fn disable_trivia(&mut self) {
-        self.trivia.disabled = true;
+    /* Settles whatever the sudden death game that just ended was played to decide */
+    fn finish_tiebreak(&mut self, ctx: &mut GameContext, winners: &[String]) {
+        match self.tiebreak.take() {
+            Some(Tiebreak::Game) => {
+                let names: Vec<String> = winners.iter()
+                                                .filter_map(|pk| get_peer_index(&self.peers, pk))
+                                                .map(|i| self.peers[i].get_nick())
+                                                .collect();
+
+                let message = match names.len() {
+                    1 => format!("{} wins the sudden death and the game!", names[0]),
+                    _ => format!("Sudden death couldn't separate them. {} share the win!", names.join(" and ")),
+                };
+
+                self.finish_game(ctx, winners, message);
+            },
+            Some(Tiebreak::Tournament) => self.crown_champions(ctx, winners),
+            None => (),
+        }
     }
 
-    pub fn next_trivia_question(&mut self, tox: &mut Tox, questions: &Vec<String>, db: &mut DataBase) {
-        if self.trivia.rounds > 0 && !self.trivia.winner && !self.trivia.answer.is_empty() {
-            let mut message = String::new();
-            write!(&mut message, "Time's up! The answer was: {}", self.trivia.answer).unwrap();
-            self.send_message(tox, &message);
-            self.trivia.end_timer = get_time();
+    /*
+     * Crowns the tournament leader once all of its games have been played. Tied leaders play
+     * sudden death in this group.
+     */
+    fn finish_tournament(&mut self, ctx: &mut GameContext) {
+        let contestants: Vec<Peer> = match *ctx.tournament {
+            Some(ref mut t) if t.stage == TournamentStage::Running && t.is_over() => {
+                t.stage = TournamentStage::Tiebreak;
+                t.tiebreak_host = Some(self.groupnumber);
+                t.leaders().iter().map(|e| Peer::new(e.public_key.to_string(), e.nick.to_string(), 0, 0)).collect()
+            },
+            _ => return,
+        };
+
+        if contestants.len() == 1 {
+            return self.crown_champions(ctx, &[contestants[0].public_key.to_string()]);
         }
 
-        if self.trivia.rounds >= MAX_ROUNDS {
-            self.end_trivia(tox, db);
-            return;
+        let names: Vec<String> = contestants.iter().map(|p| p.get_nick()).collect();
+        let message = format!("The tournament is tied between {}!", names.join(" and "));
+        ctx.send_message(&message);
+
+        self.start_tiebreak(ctx, Tiebreak::Tournament, contestants);
+    }
+
+    /* Ends the tournament, recording a title in the database for each of the winners */
+    fn crown_champions(&self, ctx: &mut GameContext, winners: &[String]) {
+        let names: Vec<String> = match *ctx.tournament {
+            Some(ref t) => winners.iter().map(|pk| t.get_nick(pk).unwrap_or("Anonymous").to_string()).collect(),
+            None        => return,
+        };
+
+        for (pk, nick) in winners.iter().zip(&names) {
+            ctx.db.add_champion(nick, pk);
         }
 
-        if !self.trivia.new_round(questions) {
-            return;
+        let message = match names.len() {
+            1 => format!("{} is the tournament champion!", names[0]),
+            _ => format!("{} share the tournament championship!", names.join(" and ")),
+        };
+
+        // Sent before the tournament is gone so every group following a tiebreak hears it
+        ctx.send_message(&message);
+        *ctx.tournament = None;
+        ctx.db.save();
+    }
+
+    /* Records the game in every team's record and returns the team standings. Teams tied for the top score all win. */
+    fn team_results(&self, ctx: &mut GameContext) -> String {
+        let scores = self.teams.scores(&self.peers);
+        let best = scores.first().map(|s| s.1).unwrap_or(0);
+        let mut winners = Vec::new();
+        let mut standings = Vec::new();
+
+        for &(name, points) in &scores {
+            let won = best > 0 && points == best;
+
+            if won {
+                winners.push(name);
+            }
+
+            standings.push(format!("{}: {}", name, points));
+            ctx.db.update_team(name, points, won);
         }
 
         let mut message = String::new();
-        write!(&mut message, "ROUND {}: {}", self.trivia.rounds, self.trivia.question).unwrap();
-        self.send_message(tox, &message);
+        write!(&mut message, "Teams: {}", standings.join(", ")).unwrap();
+
+        match winners.len() {
+            0 => (),
+            1 => write!(&mut message, "\nTeam {} wins!", winners[0]).unwrap(),
+            _ => write!(&mut message, "\nTeams {} tie for the win!", winners.join(" and ")).unwrap(),
+        }
+
+        message
+    }
+
+    pub fn abort_game(&mut self, ctx: &mut GameContext, privileged: bool) {
+        if self.game.is_none() && self.lobby.take().is_some() {
+            return ctx.send_message("Sign-ups cancelled.");
+        }
+
+        let mut game = match self.game.take() {
+            Some(game) => game,
+            None       => return,
+        };
+
+        // if we're not privileged but but we own this game of trivia we can only stop
+        // the game if no other peers have a positive score
+        if !privileged {
+            for p in &self.peers {
+                if p.round_score > 0 && p.public_key != game.owner_key() {
+                    self.game = Some(game);
+                    return;
+                }
+            }
+        }
+
+        self.last_question = game.last_question();
+        self.registered.clear();
+        game.on_end(ctx, &mut self.peers);
+
+        // An aborted tiebreak leaves the tied players sharing the win
+        if let Some(winners) = game.tiebreak_winners() {
+            ctx.send_message("Sudden death aborted.");
+            return self.finish_tiebreak(ctx, &winners);
+        }
+
+        for p in &mut self.peers {
+            p.clear_round();
+        }
+
+        ctx.send_message("Game aborted.");
+    }
+
+    pub fn enable_games(&mut self) {
+        self.disabled = false;
+    }
+
+    pub fn disable_games(&mut self) {
+        self.disabled = true;
     }
 }
 
@@ -250,3 +621,28 @@ pub fn get_peer_public_key(tox: &mut Tox, groupnumber: u32, peernumber: u32) ->
 
     public_key
 }
+
+pub fn send_group_message(tox: &mut Tox, groupnumber: u32, message: &str)
+{
+    match tox.send_conference_message(groupnumber, MessageType::Normal, message) {
+        Ok(_)  => (),
+        Err(e) => println!("Failed to send message to group {}: {:?}", groupnumber, e),
+    };
+}
+
+/* Sends a private message to the friend with public_key. Returns false if they aren't our friend. */
+pub fn send_friend_message_by_key(tox: &mut Tox, public_key: &str, message: &str) -> bool
+{
+    let friendnumber = match tox.get_friend_list().into_iter()
+                                .find(|&f| tox.get_friend_public_key(f).map(|k| k.to_string()) == Some(public_key.to_string())) {
+        Some(friendnumber) => friendnumber,
+        None               => return false,
+    };
+
+    match tox.send_friend_message(friendnumber, MessageType::Normal, message) {
+        Ok(_)  => (),
+        Err(e) => println!("Failed to send message to friend {}: {:?}", friendnumber, e),
+    };
+
+    true
+}
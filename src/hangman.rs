@@ -0,0 +1,268 @@
+/*  hangman.rs
+ *
+ *
+ *  Copyright (C) 2015 rustybot All Rights Reserved.
+ *
+ *  This file is part of rustybot.
+ *
+ *  rustybot is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  rustybot is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with rustybot. If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use rand::{Rng, thread_rng};
+use time::{get_time, Timespec};
+use std::fmt::Write;
+use game::{GameMode, GameContext};
+use group::{Peer, get_peer_index};
+use trivia::{is_punctuation, mask_answer};
+use util::*;
+
+// Number of words per game
+const MAX_WORDS: u32 = 10;
+
+// Number of wrong guesses before the word is lost
+const MAX_WRONG_GUESSES: usize = 6;
+
+// Number of seconds players have to guess a word
+const WORD_TIME_LIMIT: i64 = 120;
+
+// Seconds to wait between words
+const WORD_DELAY: i64 = 3;
+
+// Points for each hidden letter revealed by a guess
+const LETTER_POINTS: u64 = 5;
+
+// Points for solving the word, plus LETTER_POINTS for every letter still hidden
+const SOLVE_POINTS: u64 = 30;
+
+pub fn new_hangman() -> Box<dyn GameMode>
+{
+    Box::new(Hangman::new())
+}
+
+pub struct Hangman {
+    pub word:       String,      // Current word
+    pub revealed:   Vec<bool>,   // Marks which characters of the word have been revealed
+    pub wrong:      Vec<char>,   // Letters guessed that aren't in the word
+    pub words:      u32,         // Number of words played so far
+    pub word_timer: Timespec,    // Time since the current word was given
+    pub end_timer:  Timespec,    // Time since the last word ended
+    pub owner_key:  String,      // The peer who started the game
+}
+
+impl Hangman {
+    pub fn new() -> Hangman {
+        Hangman {
+            word: String::new(),
+            revealed: Vec::new(),
+            wrong: Vec::new(),
+            words: 0,
+            word_timer: Timespec::new(0, 0),
+            end_timer: Timespec::new(0, 0),
+            owner_key: String::new(),
+        }
+    }
+
+    fn is_solved(&self) -> bool {
+        self.revealed.iter().all(|r| *r)
+    }
+
+    /* Number of letters in the word that haven't been revealed */
+    fn hidden_letters(&self) -> u64 {
+        self.revealed.iter().filter(|r| !**r).count() as u64
+    }
+
+    /* Returns the masked word followed by the wrong guesses so far */
+    fn status(&self) -> String {
+        let mut status = String::new();
+        write!(&mut status, "{}", mask_answer(&self.word, &self.revealed)).unwrap();
+
+        if !self.wrong.is_empty() {
+            let wrong: Vec<String> = self.wrong.iter().map(|c| c.to_string()).collect();
+            write!(&mut status, " (Wrong: {} - {} left)", wrong.join(" "),
+                   MAX_WRONG_GUESSES - self.wrong.len()).unwrap();
+        }
+
+        status
+    }
+
+    fn end_word(&mut self) {
+        self.word.clear();
+        self.revealed.clear();
+        self.wrong.clear();
+        self.end_timer = get_time();
+    }
+
+    fn new_word(&mut self, ctx: &mut GameContext) -> bool {
+        if !timed_out(self.end_timer, WORD_DELAY) {
+            return false;
+        }
+
+        let mut rng = thread_rng();
+        let word = ctx.words[rng.gen_range(0, ctx.words.len())].trim().to_string();
+
+        if word.is_empty() {
+            return false;
+        }
+
+        self.revealed = word.chars().map(|c| is_punctuation(c)).collect();
+        self.word = word;
+        self.words += 1;
+        self.word_timer = get_time();
+
+        let mut message = String::new();
+        write!(&mut message, "WORD {}: {}", self.words, self.status()).unwrap();
+        ctx.send_message(&message);
+
+        true
+    }
+
+    /* Reveals every occurrence of letter and returns the number of characters revealed */
+    fn reveal(&mut self, letter: char) -> u64 {
+        let mut count = 0;
+
+        for (i, ch) in self.word.chars().enumerate() {
+            if !self.revealed[i] && ch.to_lowercase().eq(letter.to_lowercase()) {
+                self.revealed[i] = true;
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    fn solve(&mut self, ctx: &mut GameContext, peer: &mut Peer) {
+        let points = SOLVE_POINTS + LETTER_POINTS * self.hidden_letters();
+        peer.update_round_score(points);
+
+        let mut message = String::new();
+        write!(&mut message, "{} solved it for {} points: {} (Total: {} points)",
+               peer.get_nick(), points, self.word, peer.get_round_score()).unwrap();
+        ctx.send_message(&message);
+
+        self.end_word();
+    }
+
+    fn guess_letter(&mut self, ctx: &mut GameContext, peer: &mut Peer, letter: char) {
+        let letter = letter.to_lowercase().next().unwrap_or(letter);
+
+        if self.wrong.contains(&letter) {
+            return;
+        }
+
+        let count = self.reveal(letter);
+        let mut message = String::new();
+
+        if count > 0 {
+            if self.is_solved() {
+                return self.solve(ctx, peer);
+            }
+
+            let points = LETTER_POINTS * count;
+            peer.add_points(points);
+            write!(&mut message, "{} found {} '{}' (+{}): {}", peer.get_nick(), count, letter, points,
+                   self.status()).unwrap();
+            ctx.send_message(&message);
+            return;
+        }
+
+        if self.word.to_lowercase().contains(letter) {
+            return;  // already revealed
+        }
+
+        self.wrong.push(letter);
+
+        if self.wrong.len() >= MAX_WRONG_GUESSES {
+            write!(&mut message, "Hanged! The word was: {}", self.word).unwrap();
+            ctx.send_message(&message);
+            self.end_word();
+            return;
+        }
+
+        write!(&mut message, "No '{}': {}", letter, self.status()).unwrap();
+        ctx.send_message(&message);
+    }
+}
+
+impl GameMode for Hangman {
+    fn name(&self) -> &str {
+        "hangman"
+    }
+
+    fn owner_key(&self) -> &str {
+        &self.owner_key
+    }
+
+    fn start(&mut self, ctx: &mut GameContext, owner_key: &str, _args: &str) -> bool {
+        if ctx.words.is_empty() {
+            ctx.send_message("No words have been loaded for hangman.");
+            return false;
+        }
+
+        self.owner_key = owner_key.to_string();
+        ctx.send_message("Hangman time! Guess one letter at a time or the whole word.");
+        true
+    }
+
+    fn tick(&mut self, ctx: &mut GameContext, _peers: &mut Vec<Peer>) -> bool {
+        if self.word.is_empty() {
+            if self.words >= MAX_WORDS {
+                return true;
+            }
+
+            self.new_word(ctx);
+            return false;
+        }
+
+        if timed_out(self.word_timer, WORD_TIME_LIMIT) {
+            let mut message = String::new();
+            write!(&mut message, "Time's up! The word was: {}", self.word).unwrap();
+            ctx.send_message(&message);
+            self.end_word();
+        }
+
+        false
+    }
+
+    fn on_message(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>, public_key: &str, message: &str) {
+        if self.word.is_empty() {
+            return;
+        }
+
+        let peer_idx = match get_peer_index(peers, public_key) {
+            Some(idx) => idx,
+            None => return println!("get_peer_index() failed in hangman on_message() for public_key {:?}", public_key),
+        };
+
+        let guess = message.trim();
+        let mut chars = guess.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(letter), None) => {
+                if letter.is_alphanumeric() {
+                    self.guess_letter(ctx, &mut peers[peer_idx], letter);
+                }
+            },
+            _ => {
+                if guess.to_lowercase() == self.word.to_lowercase() {
+                    self.solve(ctx, &mut peers[peer_idx]);
+                }
+            },
+        }
+    }
+
+    fn on_end(&mut self, _ctx: &mut GameContext, _peers: &mut Vec<Peer>) {
+        *self = Hangman::new();
+    }
+}
@@ -0,0 +1,527 @@
+/*  import.rs
+ *
+ *
+ *  Copyright (C) 2015 rustybot All Rights Reserved.
+ *
+ *  This file is part of rustybot.
+ *
+ *  rustybot is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  rustybot is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with rustybot. If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+/*
+ * Converts question packs in other formats into lines for the questions file. Run as:
+ *
+ *     rustybot import <file> [json|csv] [--category name] [--dry-run]
+ *
+ * JSON files are expected in the Open Trivia DB format. CSV files may have a header row naming
+ * the question, answer, category, difficulty and incorrect answer columns; without one the
+ * columns are taken to be question, answer and category.
+ */
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::io::ErrorKind;
+use std::collections::HashSet;
+use std::fmt::Write;
+use serde_json::{self, Value};
+use questions::{QUESTIONS_PATH, parse_question, check_question, for_each_question_line, append_question_file};
+
+// Maximum number of rejected entries to list individually
+const MAX_REJECTS_SHOWN: usize = 50;
+
+const IMPORT_USAGE: &'static str = "Usage: rustybot import <file> [json|csv] [--category name] [--dry-run]";
+
+/* A question read from an import file, before it's been validated */
+struct ImportEntry {
+    location:   String,        // Where the entry came from, e.g. "line 12"
+    question:   String,
+    answer:     String,
+    category:   String,
+    difficulty: String,
+    incorrect:  Vec<String>,   // Wrong options for multiple choice questions
+}
+
+impl ImportEntry {
+    fn new(location: String) -> ImportEntry {
+        ImportEntry {
+            location: location,
+            question: String::new(),
+            answer: String::new(),
+            category: String::new(),
+            difficulty: String::new(),
+            incorrect: Vec::new(),
+        }
+    }
+
+    /* Converts the entry to a line in the questions file format */
+    fn to_line(&self) -> Result<String, String> {
+        let question = clean_text(&self.question);
+        let answer = clean_text(&self.answer);
+
+        if question.is_empty() {
+            return Err("missing question".to_string());
+        }
+
+        if answer.is_empty() {
+            return Err("missing answer".to_string());
+        }
+
+        let mut all_text = vec![question.to_string(), answer.to_string()];
+        all_text.extend(self.incorrect.iter().map(|s| clean_text(s)));
+
+        if all_text.iter().any(|s| s.contains('`')) {
+            return Err("contains the ` character".to_string());
+        }
+
+        let mut line = String::new();
+        write!(&mut line, "{}`{}", question, answer).unwrap();
+
+        let category = slugify(&self.category);
+
+        if !category.is_empty() {
+            write!(&mut line, "`category={}", category).unwrap();
+        }
+
+        let difficulty = self.difficulty.trim().to_lowercase();
+
+        if !difficulty.is_empty() {
+            write!(&mut line, "`difficulty={}", difficulty).unwrap();
+        }
+
+        let incorrect: Vec<String> = self.incorrect.iter()
+                                                   .map(|s| clean_text(s))
+                                                   .filter(|s| !s.is_empty())
+                                                   .collect();
+
+        if !incorrect.is_empty() {
+            if all_text.iter().any(|s| s.contains('|')) {
+                return Err("multiple choice option contains the | character".to_string());
+            }
+
+            write!(&mut line, "`choices={}|{}", answer, incorrect.join("|")).unwrap();
+        }
+
+        check_question(&line)?;
+        Ok(line)
+    }
+}
+
+/* Collapses whitespace and newlines so text fits on one line */
+fn clean_text(s: &str) -> String
+{
+    decode_entities(s).split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/* Turns a category like "Science: Computers" into a name that can be typed in a command: science-computers */
+fn slugify(s: &str) -> String
+{
+    let mut slug = String::new();
+
+    for ch in decode_entities(s).to_lowercase().chars() {
+        if ch.is_alphanumeric() {
+            slug.push(ch);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    slug.trim_end_matches('-').to_string()
+}
+
+/* Decodes the HTML entities Open Trivia DB uses in its default encoding */
+fn decode_entities(s: &str) -> String
+{
+    let mut decoded = String::new();
+    let mut rest = s;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = match rest.find(';') {
+            Some(end) if end <= 10 => end,
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+
+        let entity = &rest[1..end];
+
+        let ch = match entity {
+            "amp"    => Some('&'),
+            "quot"   => Some('"'),
+            "apos"   => Some('\''),
+            "lt"     => Some('<'),
+            "gt"     => Some('>'),
+            "nbsp"   => Some(' '),
+            "shy"    => Some('\u{ad}'),
+            "hellip" => Some('\u{2026}'),
+            "ldquo"  => Some('\u{201c}'),
+            "rdquo"  => Some('\u{201d}'),
+            "lsquo"  => Some('\u{2018}'),
+            "rsquo"  => Some('\u{2019}'),
+            "eacute" => Some('\u{e9}'),
+            "Eacute" => Some('\u{c9}'),
+            "uuml"   => Some('\u{fc}'),
+            "ouml"   => Some('\u{f6}'),
+            "auml"   => Some('\u{e4}'),
+            "ntilde" => Some('\u{f1}'),
+            "deg"    => Some('\u{b0}'),
+            _ => {
+                if entity.starts_with("#x") || entity.starts_with("#X") {
+                    u32::from_str_radix(&entity[2..], 16).ok().and_then(::std::char::from_u32)
+                } else if entity.starts_with('#') {
+                    entity[1..].parse::<u32>().ok().and_then(::std::char::from_u32)
+                } else {
+                    None
+                }
+            }
+        };
+
+        match ch {
+            Some(ch) => {
+                decoded.push(ch);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    decoded.push_str(rest);
+    decoded
+}
+
+fn json_string(value: &Value, key: &str) -> String
+{
+    match value.get(key) {
+        Some(&Value::String(ref s)) => s.to_string(),
+        Some(&Value::Number(ref n)) => n.to_string(),
+        _                           => String::new(),
+    }
+}
+
+/* Reads entries from an Open Trivia DB style JSON document, or a bare array of its result objects */
+fn parse_json(contents: &str) -> Result<Vec<ImportEntry>, String>
+{
+    let doc: Value = serde_json::from_str(contents).map_err(|e| format!("Invalid JSON: {}", e))?;
+
+    let results = match doc {
+        Value::Array(ref list) => list,
+        Value::Object(_) => match doc.get("results") {
+            Some(&Value::Array(ref list)) => list,
+            _ => return Err("JSON document has no results array".to_string()),
+        },
+        _ => return Err("JSON document is not an object or array".to_string()),
+    };
+
+    let mut entries = Vec::new();
+
+    for (i, result) in results.iter().enumerate() {
+        let mut entry = ImportEntry::new(format!("result {}", i + 1));
+        entry.question = json_string(result, "question");
+        entry.answer = json_string(result, "correct_answer");
+        entry.category = json_string(result, "category");
+        entry.difficulty = json_string(result, "difficulty");
+
+        if let Some(&Value::Array(ref list)) = result.get("incorrect_answers") {
+            entry.incorrect = list.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()).collect();
+        }
+
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+/* Splits CSV text into records of fields, handling quoted fields. Returns each record with its line number. */
+fn split_csv(contents: &str) -> Vec<(usize, Vec<String>)>
+{
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if quoted {
+            match ch {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                },
+                '"' => quoted = false,
+                _ => {
+                    if ch == '\n' {
+                        line += 1;
+                    }
+
+                    field.push(ch);
+                },
+            }
+
+            continue;
+        }
+
+        match ch {
+            '"' if field.is_empty() => quoted = true,
+            ',' => record.push(::std::mem::replace(&mut field, String::new())),
+            '\r' => (),
+            '\n' => {
+                record.push(::std::mem::replace(&mut field, String::new()));
+
+                if record.iter().any(|f| !f.trim().is_empty()) {
+                    records.push((record_line, ::std::mem::replace(&mut record, Vec::new())));
+                } else {
+                    record.clear();
+                }
+
+                line += 1;
+                record_line = line;
+            },
+            _ => field.push(ch),
+        }
+    }
+
+    record.push(field);
+
+    if record.iter().any(|f| !f.trim().is_empty()) {
+        records.push((record_line, record));
+    }
+
+    records
+}
+
+/* Reads entries from CSV, using the header row to find columns if there is one */
+fn parse_csv(contents: &str) -> Result<Vec<ImportEntry>, String>
+{
+    let mut records = split_csv(contents);
+
+    if records.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let (mut question_col, mut answer_col, mut category_col) = (0, 1, Some(2));
+    let mut difficulty_col = None;
+    let mut incorrect_cols = Vec::new();
+
+    let has_header = records[0].1.iter().any(|f| f.trim().to_lowercase() == "question");
+
+    if has_header {
+        let (_, header) = records.remove(0);
+        category_col = None;
+
+        for (i, name) in header.iter().enumerate() {
+            let name = name.trim().to_lowercase();
+
+            match name.as_str() {
+                "question"                    => question_col = i,
+                "answer" | "correct_answer"   => answer_col = i,
+                "category"                    => category_col = Some(i),
+                "difficulty"                  => difficulty_col = Some(i),
+                _ if name.starts_with("incorrect") || name.starts_with("wrong") => incorrect_cols.push(i),
+                _ => (),
+            }
+        }
+    }
+
+    let mut entries = Vec::new();
+
+    for (line, record) in records {
+        let field = |col: usize| record.get(col).map(|f| f.to_string()).unwrap_or(String::new());
+
+        let mut entry = ImportEntry::new(format!("line {}", line));
+        entry.question = field(question_col);
+        entry.answer = field(answer_col);
+        entry.category = category_col.map(|c| field(c)).unwrap_or(String::new());
+        entry.difficulty = difficulty_col.map(|c| field(c)).unwrap_or(String::new());
+        entry.incorrect = incorrect_cols.iter().map(|c| field(*c)).collect();
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+/* Runs the import subcommand. Returns an error message if the import couldn't be done. */
+pub fn run_import(args: &[String]) -> Result<(), String>
+{
+    let mut path = None;
+    let mut format = None;
+    let mut category = None;
+    let mut dry_run = false;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "json" | "csv" => format = Some(arg.to_string()),
+            "--dry-run"    => dry_run = true,
+            "--category"   => match iter.next() {
+                Some(c) if !c.starts_with("--") => category = Some(c.to_string()),
+                _ => return Err(format!("--category needs a name\n{}", IMPORT_USAGE)),
+            },
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}\n{}", arg, IMPORT_USAGE)),
+            _ => path = Some(arg.to_string()),
+        }
+    }
+
+    let path = match path {
+        Some(path) => path,
+        None       => return Err(IMPORT_USAGE.to_string()),
+    };
+
+    let format = match format {
+        Some(format) => format,
+        None => if path.to_lowercase().ends_with(".csv") { "csv".to_string() } else { "json".to_string() },
+    };
+
+    let mut contents = String::new();
+    let mut fp = File::open(&path).map_err(|e| format!("Open failed on file {}: {}", path, e))?;
+    fp.read_to_string(&mut contents).map_err(|e| format!("Read failed on file {}: {}", path, e))?;
+
+    let mut entries = if format == "csv" { parse_csv(&contents)? } else { parse_json(&contents)? };
+
+    if let Some(category) = category {
+        for entry in entries.iter_mut() {
+            entry.category = category.to_string();
+        }
+    }
+
+    // Existing questions are compared case-insensitively to catch near duplicates
+    let mut seen: HashSet<String> = HashSet::new();
+
+    // A missing questions file only means there's nothing to compare against yet
+    match fs::metadata(QUESTIONS_PATH) {
+        Err(ref e) if e.kind() == ErrorKind::NotFound => (),
+        _ => for_each_question_line(|_, line| {
+            if let Some(q) = parse_question(line) {
+                seen.insert(q.question.to_lowercase());
+            }
+        })?,
+    };
+
+    let mut accepted = Vec::new();
+    let mut rejected = Vec::new();
+    let mut duplicates = 0;
+
+    for entry in &entries {
+        let line = match entry.to_line() {
+            Ok(line) => line,
+            Err(e) => {
+                rejected.push(format!("{}: {}", entry.location, e));
+                continue;
+            }
+        };
+
+        let key = clean_text(&entry.question).to_lowercase();
+
+        if !seen.insert(key) {
+            duplicates += 1;
+            continue;
+        }
+
+        accepted.push(line);
+    }
+
+    if !dry_run {
+        for line in &accepted {
+            append_question_file(line)?;
+        }
+    }
+
+    println!("{} entries read from {}", entries.len(), path);
+    println!("{} questions {}", accepted.len(), if dry_run { "would be imported" } else { "imported" });
+    println!("{} duplicates skipped", duplicates);
+    println!("{} rejected", rejected.len());
+
+    for reject in rejected.iter().take(MAX_REJECTS_SHOWN) {
+        println!("  {}", reject);
+    }
+
+    if rejected.len() > MAX_REJECTS_SHOWN {
+        println!("  ... and {} more", rejected.len() - MAX_REJECTS_SHOWN);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_quoting() {
+        let records = split_csv("a,\"b, c\",\"say \"\"hi\"\"\"\r\n\n\"multi\nline\",x\n");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0], (1, vec!["a".to_string(), "b, c".to_string(), "say \"hi\"".to_string()]));
+        assert_eq!(records[1], (3, vec!["multi\nline".to_string(), "x".to_string()]));
+    }
+
+    #[test]
+    fn csv_columns() {
+        let entries = parse_csv("Answer,Question,Difficulty,Wrong 1,Wrong 2\nParis,\"Capital of France?\",easy,Rome,Berlin\n")
+                                .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].location, "line 2");
+        assert_eq!(entries[0].to_line(), Ok("Capital of France?`Paris`difficulty=easy`choices=Paris|Rome|Berlin"
+                                            .to_string()));
+
+        let entries = parse_csv("What is 2+2?,4,Science: Maths\n").unwrap();
+        assert_eq!(entries[0].to_line(), Ok("What is 2+2?`4`category=science-maths".to_string()));
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(decode_entities("Tom &amp; Jerry"), "Tom & Jerry");
+        assert_eq!(decode_entities("&quot;Hi&quot; &#039;there&#039;"), "\"Hi\" 'there'");
+        assert_eq!(decode_entities("caf&eacute; &#x41;"), "caf\u{e9} A");
+        assert_eq!(decode_entities("R&D; 5 & 6"), "R&D; 5 & 6");
+        assert_eq!(decode_entities("&amp"), "&amp");
+    }
+
+    #[test]
+    fn json_results() {
+        let json = r#"{"results": [{"category": "Entertainment: Film", "difficulty": "hard",
+                       "question": "Who directed &quot;Jaws&quot;?", "correct_answer": "Steven Spielberg",
+                       "incorrect_answers": ["George Lucas", "James Cameron"]}]}"#;
+        let entries = parse_json(json).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].to_line(), Ok("Who directed \"Jaws\"?`Steven Spielberg`category=entertainment-film\
+                                             `difficulty=hard`choices=Steven Spielberg|George Lucas|James Cameron"
+                                            .to_string()));
+
+        assert!(parse_json("{\"response_code\": 0}").is_err());
+        assert!(parse_json("not json").is_err());
+    }
+
+    #[test]
+    fn rejected_entries() {
+        let mut entry = ImportEntry::new("line 1".to_string());
+        entry.question = "Question?".to_string();
+        assert_eq!(entry.to_line(), Err("missing answer".to_string()));
+
+        entry.answer = "a`b".to_string();
+        assert_eq!(entry.to_line(), Err("contains the ` character".to_string()));
+
+        entry.answer = "answer".to_string();
+        entry.difficulty = "hardest".to_string();
+        assert_eq!(entry.to_line(), Err("unknown difficulty \"hardest\"".to_string()));
+    }
+}
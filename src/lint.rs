@@ -0,0 +1,256 @@
+/*  lint.rs
+ *
+ *
+ *  Copyright (C) 2015 rustybot All Rights Reserved.
+ *
+ *  This file is part of rustybot.
+ *
+ *  rustybot is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  rustybot is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with rustybot. If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use std::collections::{BTreeMap, HashMap};
+use questions::{Question, QuestionBank, check_question, for_each_question_line, question_file_stamp, QUESTIONS_PATH};
+use stats::Difficulty;
+
+// Answers longer than this are hard to type before time runs out
+const MAX_ANSWER_LENGTH: usize = 60;
+
+// Maximum number of problems printed when questions are loaded by the bot
+const MAX_LOAD_ISSUES_SHOWN: usize = 10;
+
+#[derive(PartialEq)]
+pub enum Severity {
+    Error,     // The line is skipped
+    Warning,   // The question is loaded but should probably be fixed
+}
+
+pub struct LintIssue {
+    pub line:     usize,   // Line number in the questions file, starting from 1
+    pub severity: Severity,
+    pub message:  String,
+}
+
+/*
+ * Checks the lines of a questions file one at a time. Malformed lines and repeats of an earlier
+ * question are errors; blank lines are ignored.
+ */
+pub struct Linter {
+    pub issues: Vec<LintIssue>,
+    seen:       HashMap<String, usize>,   // Lowercased question -> the line it was first seen on
+}
+
+impl Linter {
+    pub fn new() -> Linter {
+        Linter {
+            issues: Vec::new(),
+            seen: HashMap::new(),
+        }
+    }
+
+    /* Returns the question on the line if it passes */
+    pub fn check(&mut self, line_num: usize, line: &str) -> Option<Question> {
+        if line.trim().is_empty() {
+            return None;
+        }
+
+        let question = match check_question(line) {
+            Ok(question) => question,
+            Err(e) => {
+                self.issues.push(LintIssue { line: line_num, severity: Severity::Error, message: e });
+                return None;
+            }
+        };
+
+        let key = question.question.to_lowercase();
+
+        if let Some(first) = self.seen.get(&key) {
+            let message = format!("duplicate of the question on line {}", first);
+            self.issues.push(LintIssue { line: line_num, severity: Severity::Error, message: message });
+            return None;
+        }
+
+        self.seen.insert(key, line_num);
+
+        if question.answer.chars().count() > MAX_ANSWER_LENGTH {
+            let message = format!("answer is longer than {} characters", MAX_ANSWER_LENGTH);
+            self.issues.push(LintIssue { line: line_num, severity: Severity::Warning, message: message });
+        }
+
+        Some(question)
+    }
+
+    pub fn errors(&self) -> usize {
+        self.issues.iter().filter(|i| i.severity == Severity::Error).count()
+    }
+
+    pub fn warnings(&self) -> usize {
+        self.issues.iter().filter(|i| i.severity == Severity::Warning).count()
+    }
+
+    /* Prints up to max issues, one per line */
+    fn print_issues(&self, max: usize) {
+        for issue in self.issues.iter().take(max) {
+            let label = match issue.severity {
+                Severity::Error   => "error",
+                Severity::Warning => "warning",
+            };
+
+            println!("  line {}: {}: {}", issue.line, label, issue.message);
+        }
+
+        if self.issues.len() > max {
+            println!("  ... and {} more", self.issues.len() - max);
+        }
+    }
+}
+
+/*
+ * Loads the question bank from the index if it's up to date. Otherwise every line of the questions
+ * file is checked, a summary of any problems is printed, and the index is rebuilt.
+ */
+pub fn load_questions() -> Result<QuestionBank, String>
+{
+    if let Some(bank) = QuestionBank::load_index() {
+        return Ok(bank);
+    }
+
+    println!("Question index is missing or out of date; rebuilding...");
+
+    let mut bank = QuestionBank::new();
+    let mut linter = Linter::new();
+    let stamp = question_file_stamp();
+    let mut line_num = 0;
+
+    for_each_question_line(|offset, line| {
+        line_num += 1;
+
+        if let Some(question) = linter.check(line_num, line) {
+            bank.add(&question, offset, line.len());
+        }
+    })?;
+
+    if !linter.issues.is_empty() {
+        println!("{} errors and {} warnings in {} (run `rustybot check-questions` for the full list):",
+                 linter.errors(), linter.warnings(), QUESTIONS_PATH);
+        linter.print_issues(MAX_LOAD_ISSUES_SHOWN);
+    }
+
+    // The bank describes the file as it was before we read it. If it changed while we were
+    // reading, the bank is stale and will be rebuilt again.
+    if let Some(stamp) = stamp {
+        bank.set_file_stamp(stamp);
+
+        if Some(stamp) == question_file_stamp() {
+            bank.save_index();
+        }
+    }
+
+    Ok(bank)
+}
+
+/* Runs the check-questions subcommand, which lists every problem in the questions file along with some statistics */
+pub fn run_check_questions() -> Result<(), String>
+{
+    let mut linter = Linter::new();
+    let mut categories: BTreeMap<String, usize> = BTreeMap::new();
+    let mut difficulties: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut valid = 0;
+    let mut multiple_choice = 0;
+    let mut retired = 0;
+    let mut line_num = 0;
+
+    for_each_question_line(|_, line| {
+        line_num += 1;
+
+        let q = match linter.check(line_num, line) {
+            Some(q) => q,
+            None    => return,
+        };
+
+        valid += 1;
+
+        if q.retired {
+            retired += 1;
+            return;
+        }
+
+        *categories.entry(q.category.to_string()).or_insert(0) += 1;
+
+        let difficulty = q.difficulty.as_ref().map(Difficulty::name).unwrap_or("unrated");
+        *difficulties.entry(difficulty).or_insert(0) += 1;
+
+        if !q.choices.is_empty() {
+            multiple_choice += 1;
+        }
+    })?;
+
+    if linter.issues.is_empty() {
+        println!("No problems found in {}", QUESTIONS_PATH);
+    } else {
+        println!("{} errors and {} warnings in {}:", linter.errors(), linter.warnings(), QUESTIONS_PATH);
+        linter.print_issues(linter.issues.len());
+    }
+
+    println!("");
+    println!("{} valid questions, {} in play, {} retired", valid, valid - retired, retired);
+    println!("{} multiple choice or true/false", multiple_choice);
+
+    let difficulties: Vec<String> = difficulties.iter().map(|(name, n)| format!("{}: {}", name, n)).collect();
+    println!("Difficulty: {}", difficulties.join(", "));
+
+    println!("Categories:");
+
+    for (name, n) in &categories {
+        println!("  {}: {}", name, n);
+    }
+
+    if valid == retired {
+        return Err("There are no questions in play".to_string());
+    }
+
+    if linter.errors() > 0 {
+        return Err(format!("{} lines could not be loaded", linter.errors()));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicates() {
+        let mut linter = Linter::new();
+        assert!(linter.check(1, "Capital of France?`Paris").is_some());
+        assert!(linter.check(2, "Capital of Spain?`Madrid").is_some());
+        assert!(linter.check(3, "").is_none());
+        assert!(linter.check(4, "CAPITAL OF FRANCE?`paris`category=geography").is_none());
+        assert!(linter.check(5, "Capital of France?!`Paris").is_some());
+
+        assert_eq!(linter.errors(), 1);
+        assert_eq!(linter.issues[0].line, 4);
+        assert_eq!(linter.issues[0].message, "duplicate of the question on line 1");
+    }
+
+    #[test]
+    fn malformed_lines_are_not_remembered() {
+        let mut linter = Linter::new();
+        assert!(linter.check(1, "Capital of France?`").is_none());
+        assert!(linter.check(2, "Capital of France?`Paris").is_some());
+        assert_eq!(linter.errors(), 1);
+        assert_eq!(linter.issues[0].line, 1);
+    }
+}
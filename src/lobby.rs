@@ -0,0 +1,89 @@
+/*  lobby.rs
+ *
+ *
+ *  Copyright (C) 2015 rustybot All Rights Reserved.
+ *
+ *  This file is part of rustybot.
+ *
+ *  rustybot is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  rustybot is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with rustybot. If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use time::{get_time, Timespec};
+use util::*;
+
+// Game option that opens sign-ups instead of starting the game right away
+pub const LOBBY_OPTION: &'static str = "lobby";
+
+// Players needed before the countdown to the game begins
+pub const LOBBY_MIN_PLAYERS: usize = 2;
+
+// Seconds between enough players joining and the game starting
+pub const LOBBY_COUNTDOWN: i64 = 30;
+
+// Seconds sign-ups stay open waiting for enough players
+const LOBBY_TIMEOUT: i64 = 300;
+
+/* Sign-ups for a game that starts a little while after enough players have joined */
+pub struct Lobby {
+    pub game:      String,              // Name of the game mode to start
+    pub owner_key: String,              // Public key of the peer who opened sign-ups
+    pub args:      String,              // Options to start the game with
+    pub players:   Vec<String>,         // Public keys of the players who've joined
+    opened:        Timespec,            // Time since sign-ups opened
+    countdown:     Option<Timespec>,    // Time since the countdown began, once there are enough players
+}
+
+impl Lobby {
+    /* The peer who opens sign-ups is the first to join */
+    pub fn new(game: &str, owner_key: &str, args: &str) -> Lobby {
+        Lobby {
+            game: game.to_string(),
+            owner_key: owner_key.to_string(),
+            args: args.to_string(),
+            players: vec![owner_key.to_string()],
+            opened: get_time(),
+            countdown: None,
+        }
+    }
+
+    /* Adds public_key to the players. Returns true if this started the countdown. */
+    pub fn join(&mut self, public_key: &str) -> Result<bool, String> {
+        if self.players.iter().any(|p| p == public_key) {
+            return Err("You've already joined.".to_string());
+        }
+
+        self.players.push(public_key.to_string());
+
+        if self.countdown.is_some() || self.players.len() < LOBBY_MIN_PLAYERS {
+            return Ok(false);
+        }
+
+        self.countdown = Some(get_time());
+        Ok(true)
+    }
+
+    /* Returns true once the countdown has run out */
+    pub fn is_ready(&self) -> bool {
+        match self.countdown {
+            Some(t) => timed_out(t, LOBBY_COUNTDOWN),
+            None    => false,
+        }
+    }
+
+    /* Returns true if too few players joined in time */
+    pub fn is_expired(&self) -> bool {
+        self.countdown.is_none() && timed_out(self.opened, LOBBY_TIMEOUT)
+    }
+}
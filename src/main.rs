@@ -27,6 +27,8 @@ extern crate time;
 extern crate rstox;
 extern crate byteorder;
 extern crate ctrlc;
+extern crate serde_json;
+extern crate signal_hook;
 
 use std::fs::File;
 use std::path::Path;
@@ -42,16 +44,32 @@ use std::sync::Arc;
 mod db;
 mod util;
 use util::*;
+mod game;
+use self::game::*;
 mod trivia;
-use self::trivia::*;
+mod scramble;
+mod hangman;
+mod math;
+mod suddendeath;
+mod numbers;
+mod questions;
+mod stats;
+mod reports;
+mod submissions;
+mod import;
+mod lint;
+use self::questions::{QuestionBank, question_file_stamp};
+mod teams;
+mod lobby;
+mod tournament;
 mod group;
 use self::group::*;
 mod bot;
 use self::bot::*;
 mod commands;
-use self::commands::execute;
+use self::commands::{execute, execute_friend};
 
-const QUESTIONS_PATH: &'static str = "data/questions";
+const WORDS_PATH: &'static str = "data/words";
 const MASTERKEYS_PATH: &'static str = "data/masterkeys";
 const DHT_NODES_PATH: &'static str = "data/DHTnodes";
 
@@ -61,6 +79,9 @@ const BOOTSTRAP_INTERVAL: i64 = 10;
 // Number of random bootstrap nodes to connect to per try
 const MAX_BOOTSTRAP_NODES: usize = 5;
 
+// Seconds between checks for changes made to the questions file while we're running
+const QUESTIONS_CHECK_INTERVAL: i64 = 5;
+
 // Use in case DHTnodes file fails to load
 const BOOTSTRAP_IP: &'static str = "144.217.167.73";
 const BOOTSTRAP_PORT: u16 = 33445;
@@ -193,36 +214,54 @@ fn bootstrap_tox(bot: &mut Bot)
     }
 }
 
-fn load_trivia_questions(bot: &mut Bot) -> Result<(), String>
+fn load_trivia_questions() -> Result<QuestionBank, String>
 {
-
     println!("Loading trivia questions...");
+    lint::load_questions()
+}
+
+/*
+ * Reads the questions file again and swaps the new questions in. Running games keep the question
+ * they're on since they hold their own copy, and draw from the new set from the next round on.
+ * The current set is kept if the file can't be read or has no valid questions.
+ */
+pub fn reload_questions(bot: &mut Bot) -> Result<usize, String>
+{
+    let questions = load_trivia_questions()?;
+
+    if questions.is_empty() {
+        return Err("No valid questions were found; keeping the current set".to_string());
+    }
+
+    bot.questions = questions;
+    Ok(bot.questions.len())
+}
+
+fn load_scramble_words(bot: &mut Bot) -> Result<(), String>
+{
+    println!("Loading scramble words...");
 
-    let path = Path::new(QUESTIONS_PATH);
+    let path = Path::new(WORDS_PATH);
     let display = path.display();
-    let mut questions = String::new();
+    let mut words = String::new();
 
     let mut fp = File::open(&path).map_err(|e| format!("Open failed on file {}: {}", display, e))?;
-    fp.read_to_string(&mut questions).map_err(|e| format!("Read failed on file {}: {}", display, e))?;
+    fp.read_to_string(&mut words).map_err(|e| format!("Read failed on file {}: {}", display, e))?;
+
+    for line in words.split("\n") {
+        let word = line.trim();
 
-    for line in questions.split("\n") {
-        bot.questions.push(line.to_string());
+        if !word.is_empty() {
+            bot.words.push(word.to_string());
+        }
     }
 
     Ok(())
 }
 
-// Returns true if peernumber is in the masterkeys list or is the owner of groupnumber
-fn check_privilege(bot: &mut Bot, groupnumber: u32, peernumber: u32) -> bool
+// Returns true if public_key is in the masterkeys list
+fn is_master_key(public_key: &str) -> bool
 {
-    let public_key = match get_peer_public_key(bot.tox, groupnumber, peernumber) {
-        Some(key) => key.to_string(),
-        None => {
-            println!("Failed to fetch peer {}'s key in group {}", peernumber, groupnumber);
-            return false;
-        }
-    };
-
     let path = Path::new(MASTERKEYS_PATH);
     let display = path.display();
 
@@ -245,11 +284,41 @@ fn check_privilege(bot: &mut Bot, groupnumber: u32, peernumber: u32) -> bool
     };
 
     for key in keys.split("\n") {
-        if key.contains(&public_key) {
+        if key.contains(public_key) {
             return true;
         }
     }
 
+    false
+}
+
+// Returns true if friendnumber is in the masterkeys list
+fn check_friend_privilege(bot: &mut Bot, friendnumber: u32) -> bool
+{
+    match bot.tox.get_friend_public_key(friendnumber) {
+        Some(key) => is_master_key(&key.to_string()),
+        None => {
+            println!("Failed to fetch friend {}'s key", friendnumber);
+            false
+        }
+    }
+}
+
+// Returns true if peernumber is in the masterkeys list or is the owner of groupnumber
+fn check_privilege(bot: &mut Bot, groupnumber: u32, peernumber: u32) -> bool
+{
+    let public_key = match get_peer_public_key(bot.tox, groupnumber, peernumber) {
+        Some(key) => key.to_string(),
+        None => {
+            println!("Failed to fetch peer {}'s key in group {}", peernumber, groupnumber);
+            return false;
+        }
+    };
+
+    if is_master_key(&public_key) {
+        return true;
+    }
+
     for g in &bot.groups {
         if g.groupnumber != groupnumber {
             continue;
@@ -345,7 +414,20 @@ fn cb_group_message(bot: &mut Bot, groupnumber: u32, peernumber: u32, message: &
     if message.as_bytes()[0] == b'!' {
         execute(bot, groupnumber, peernumber, message);
     } else {
-        process_answer(bot, groupnumber, peernumber, message);
+        process_message(bot, groupnumber, peernumber, message);
+    }
+}
+
+fn cb_friend_message(bot: &mut Bot, friendnumber: u32, message: &str)
+{
+    if message.is_empty() {
+        return;
+    }
+
+    if message.as_bytes()[0] == b'!' {
+        execute_friend(bot, friendnumber, message);
+    } else {
+        process_private_message(bot, friendnumber, message);
     }
 }
 
@@ -362,6 +444,8 @@ fn do_tox(bot: &mut Bot)
                 cb_connection_status(bot, status),
             FriendRequest(id, message) =>
                 cb_friend_request(bot, id, &message),
+            FriendMessage(friend, _kind, message) =>
+                cb_friend_message(bot, friend, &message),
             ConferenceInvite { friend, kind, cookie } =>
                 cb_group_invite(bot, friend, kind, &cookie),
             ConferencePeerListChanged { conference } =>
@@ -387,7 +471,7 @@ fn do_connection(bot: &mut Bot)
 fn do_rustybot(bot: &mut Bot)
 {
     do_tox(bot);
-    do_trivia(bot);
+    do_games(bot);
     do_connection(bot);
 }
 
@@ -401,6 +485,41 @@ fn kill(bot: &mut Bot)
 
 fn main()
 {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() > 1 && args[1] == "import" {
+        if let Err(e) = import::run_import(&args[2..]) {
+            println!("Import failed: {}", e);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    if args.len() > 1 && args[1] == "check-questions" {
+        if let Err(e) = lint::run_check_questions() {
+            println!("Check failed: {}", e);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    let questions = match load_trivia_questions() {
+        Ok(questions) => questions,
+        Err(e) => {
+            println!("Trivia questions failed to load: {}", e);
+            return;
+        }
+    };
+
+    if questions.is_empty() {
+        println!("No valid trivia questions were found. Refusing to start.");
+        return;
+    }
+
+    println!("Loaded {} questions.", questions.len());
+
     let mut tox = match load_tox() {
         Some(tox) => tox,
         None      => return,
@@ -411,10 +530,15 @@ fn main()
     bot.save();
     bot.print_info();
     bot.db.load();
+    bot.qstats.load();
+    bot.reports.load();
+    bot.submissions.load();
+
+    bot.questions = questions;
 
-    match load_trivia_questions(&mut bot) {
-        Ok(_)  => println!("Loaded."),
-        Err(e) => println!("Trivia questions failed to load: {}", e),
+    match load_scramble_words(&mut bot) {
+        Ok(_)  => println!("Loaded {} words.", bot.words.len()),
+        Err(e) => println!("Scramble words failed to load: {}", e),
     }
 
     let running = Arc::new(AtomicBool::new(true));
@@ -423,7 +547,42 @@ fn main()
     ctrlc::set_handler(move || { r.store(false, Ordering::SeqCst); })
                       .expect("Error setting Ctrl-C handler");
 
+    // SIGHUP asks for the question file to be reloaded
+    let reload = Arc::new(AtomicBool::new(false));
+
+    signal_hook::flag::register(signal_hook::consts::SIGHUP, reload.clone())
+                              .expect("Error setting SIGHUP handler");
+
+    // Stamp of a changed questions file that failed to reload, so we don't keep retrying it
+    let mut failed_stamp = None;
+    let mut last_questions_check = get_time();
+
     while running.load(Ordering::SeqCst) {
+        if reload.swap(false, Ordering::SeqCst) {
+            match reload_questions(&mut bot) {
+                Ok(n)  => println!("Reloaded {} questions.", n),
+                Err(e) => println!("Question reload failed: {}", e),
+            }
+        }
+
+        // The index is useless once the file is changed under us, e.g. by the import command
+        if timed_out(last_questions_check, QUESTIONS_CHECK_INTERVAL) {
+            last_questions_check = get_time();
+            let stamp = question_file_stamp();
+
+            if bot.questions.is_stale() && stamp != failed_stamp {
+                println!("The questions file has changed; reloading...");
+
+                match reload_questions(&mut bot) {
+                    Ok(n)  => println!("Reloaded {} questions.", n),
+                    Err(e) => {
+                        println!("Question reload failed: {}", e);
+                        failed_stamp = stamp;
+                    },
+                }
+            }
+        }
+
         do_rustybot(&mut bot);
     }
 
@@ -0,0 +1,221 @@
+/*  math.rs
+ *
+ *
+ *  Copyright (C) 2015 rustybot All Rights Reserved.
+ *
+ *  This file is part of rustybot.
+ *
+ *  rustybot is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  rustybot is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with rustybot. If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use rand::{Rng, thread_rng};
+use std::fmt::Write;
+use game::{GameMode, GameContext};
+use trivia::{Trivia, QuestionSource};
+use questions::{Question, AnswerKind};
+
+pub struct MathLevel {
+    pub name:        &'static str,
+    pub ops:         &'static str,   // Operations problems are made from: any of + - * /
+    pub max_operand: i64,            // Largest operand for addition and subtraction
+    pub max_factor:  i64,            // Largest operand for multiplication and division
+}
+
+pub const MATH_LEVELS: [MathLevel; 3] = [
+    MathLevel { name: "easy",   ops: "+-",   max_operand: 20,  max_factor: 0  },
+    MathLevel { name: "medium", ops: "+-*",  max_operand: 100, max_factor: 12 },
+    MathLevel { name: "hard",   ops: "+-*/", max_operand: 999, max_factor: 25 },
+];
+
+// Category name used to mix math problems into trivia games
+pub const MATH_CATEGORY: &'static str = "math";
+
+// Level used when none is given
+const DEFAULT_LEVEL: usize = 1;
+
+// Operations that may be chosen with ops=
+const MATH_OPS: &'static str = "+-*/";
+
+// Largest value allowed for max=
+const MAX_OPERAND_LIMIT: i64 = 1000000;
+
+// Largest value allowed for factor=
+const MAX_FACTOR_LIMIT: i64 = 1000;
+
+// Factor limit used when multiplication or division is added to a level that has none
+const DEFAULT_MAX_FACTOR: i64 = 12;
+
+const MATH_USAGE: &'static str = "Usage: !math [easy|medium|hard] [ops=+-*/] [max=<largest number to add or subtract>] \
+                                  [factor=<largest number to multiply or divide by>]";
+
+/* The settings problems are generated with: a level, possibly with some of its settings changed */
+#[derive(Clone)]
+pub struct MathSettings {
+    pub ops:         String,
+    pub max_operand: i64,
+    pub max_factor:  i64,
+}
+
+impl MathSettings {
+    pub fn from_level(level: &MathLevel) -> MathSettings {
+        MathSettings {
+            ops: level.ops.to_string(),
+            max_operand: level.max_operand,
+            max_factor: level.max_factor,
+        }
+    }
+
+    /*
+     * Parses the options for a math game: an optional level followed by any of ops=, max= and
+     * factor=, which change that level's settings, e.g. "easy ops=+-* factor=5"
+     */
+    pub fn parse(args: &str) -> Result<MathSettings, String> {
+        let mut settings = MathSettings::from_level(default_math_level());
+
+        for (i, word) in args.split_whitespace().enumerate() {
+            let word = word.to_lowercase();
+
+            if i == 0 {
+                if let Some(level) = math_level(&word) {
+                    settings = MathSettings::from_level(level);
+                    continue;
+                }
+            }
+
+            let mut kv = word.splitn(2, '=');
+            let key = kv.next().unwrap_or("");
+            let value = match kv.next() {
+                Some(value) => value,
+                None        => return Err(MATH_USAGE.to_string()),
+            };
+
+            match key {
+                "ops" => {
+                    let mut ops = String::new();
+
+                    for op in value.chars() {
+                        if !MATH_OPS.contains(op) {
+                            return Err(format!("Unknown operation {}. Choose from {}", op, MATH_OPS));
+                        }
+
+                        if !ops.contains(op) {
+                            ops.push(op);
+                        }
+                    }
+
+                    if ops.is_empty() {
+                        return Err(MATH_USAGE.to_string());
+                    }
+
+                    settings.ops = ops;
+                },
+                "max" => match value.parse::<i64>() {
+                    Ok(n) if n >= 1 && n <= MAX_OPERAND_LIMIT => settings.max_operand = n,
+                    _ => return Err(format!("max must be a whole number from 1 to {}", MAX_OPERAND_LIMIT)),
+                },
+                "factor" => match value.parse::<i64>() {
+                    Ok(n) if n >= 2 && n <= MAX_FACTOR_LIMIT => settings.max_factor = n,
+                    _ => return Err(format!("factor must be a whole number from 2 to {}", MAX_FACTOR_LIMIT)),
+                },
+                _ => return Err(MATH_USAGE.to_string()),
+            }
+        }
+
+        // Easy has no factor limit since it has no multiplication or division
+        if settings.max_factor < 2 && (settings.ops.contains('*') || settings.ops.contains('/')) {
+            settings.max_factor = DEFAULT_MAX_FACTOR;
+        }
+
+        Ok(settings)
+    }
+}
+
+/* Returns the difficulty level called name */
+pub fn math_level(name: &str) -> Option<&'static MathLevel>
+{
+    MATH_LEVELS.iter().find(|l| l.name == name)
+}
+
+pub fn default_math_level() -> &'static MathLevel
+{
+    &MATH_LEVELS[DEFAULT_LEVEL]
+}
+
+/* Generates a random arithmetic problem with the given settings. */
+pub fn generate_problem(settings: &MathSettings) -> Question
+{
+    let mut rng = thread_rng();
+    let ops: Vec<char> = settings.ops.chars().collect();
+    let op = ops[rng.gen_range(0, ops.len())];
+
+    let (a, b, answer) = match op {
+        '+' => {
+            let a = rng.gen_range(1, settings.max_operand + 1);
+            let b = rng.gen_range(1, settings.max_operand + 1);
+            (a, b, a + b)
+        },
+        '-' => {
+            let a = rng.gen_range(1, settings.max_operand + 1);
+            let b = rng.gen_range(1, a + 1);
+            (a, b, a - b)
+        },
+        '*' => {
+            let a = rng.gen_range(2, settings.max_factor + 1);
+            let b = rng.gen_range(2, settings.max_factor + 1);
+            (a, b, a * b)
+        },
+        _ => {
+            // Division problems always have whole number answers
+            let b = rng.gen_range(2, settings.max_factor + 1);
+            let answer = rng.gen_range(2, settings.max_factor + 1);
+            (b * answer, b, answer)
+        },
+    };
+
+    let symbol = match op {
+        '*' => 'x',
+        op  => op,
+    };
+
+    let mut question = String::new();
+    write!(&mut question, "Math: {} {} {} = ?", a, symbol, b).unwrap();
+
+    Question::new(&question, &answer.to_string(), AnswerKind::Integer)
+}
+
+/* Generates arithmetic problems for a standalone math game */
+pub struct MathProblems {
+    settings: MathSettings,
+}
+
+impl QuestionSource for MathProblems {
+    fn configure(&mut self, _ctx: &GameContext, args: &str) -> Result<(), String> {
+        self.settings = MathSettings::parse(args)?;
+        Ok(())
+    }
+
+    fn is_empty(&self, _ctx: &GameContext) -> bool {
+        false
+    }
+
+    fn next_question(&mut self, _ctx: &GameContext) -> Option<Question> {
+        Some(generate_problem(&self.settings))
+    }
+}
+
+pub fn new_math() -> Box<dyn GameMode>
+{
+    Box::new(Trivia::new("math", "Math time!", Box::new(MathProblems { settings: MathSettings::from_level(default_math_level()) })))
+}
@@ -0,0 +1,301 @@
+/*  numbers.rs
+ *
+ *
+ *  Copyright (C) 2015 rustybot All Rights Reserved.
+ *
+ *  This file is part of rustybot.
+ *
+ *  rustybot is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  rustybot is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with rustybot. If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+/*
+ * Parsing for numeric answers. Players may type numbers with digits or words ("206", "206.",
+ * "two hundred and six") and may add a unit ("206 bones", "5km", "$20").
+ */
+
+// Number words below twenty, indexed by value
+const SMALL_NUMBERS: [&'static str; 20] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+    "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen",
+];
+
+// Multiples of ten, indexed by value / 10
+const TENS: [&'static str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+
+const SCALES: [(&'static str, f64); 4] = [
+    ("thousand", 1e3),
+    ("million",  1e6),
+    ("billion",  1e9),
+    ("trillion", 1e12),
+];
+
+// Currency symbols that may come before a number. They're treated as the number's unit.
+const CURRENCY_SYMBOLS: &'static str = "$£€¥";
+
+// Spellings of common units and the short form they're compared as
+const UNIT_ALIASES: [(&'static str, &'static str); 30] = [
+    ("kilometer", "km"), ("kilometre", "km"), ("kilometers", "km"), ("kilometres", "km"),
+    ("meter", "m"), ("metre", "m"), ("meters", "m"), ("metres", "m"),
+    ("centimeter", "cm"), ("centimetre", "cm"), ("millimeter", "mm"), ("millimetre", "mm"),
+    ("mile", "mi"), ("miles", "mi"), ("foot", "ft"), ("feet", "ft"), ("inch", "in"), ("inches", "in"),
+    ("kilogram", "kg"), ("kilograms", "kg"), ("gram", "g"), ("grams", "g"),
+    ("pound", "lb"), ("pounds", "lb"), ("lbs", "lb"),
+    ("second", "s"), ("sec", "s"), ("minute", "min"), ("hour", "h"), ("%", "percent"),
+];
+
+/*
+ * Parses a number written with digits. Thousands separators (, and _) and a trailing period are ignored,
+ * but a space inside the number means it's really two numbers, as in "20 6".
+ * Rust's float parsing also takes "nan", "inf" and numbers too big to represent, none of which are answers.
+ */
+pub fn parse_digits(s: &str) -> Option<f64>
+{
+    let s = s.trim().trim_end_matches('.');
+    let cleaned: String = s.chars().filter(|c| *c != ',' && *c != '_').collect();
+
+    if cleaned.is_empty() {
+        return None;
+    }
+
+    cleaned.parse::<f64>().ok().filter(|n| n.is_finite())
+}
+
+/* Parses a number written in words, e.g. "two hundred and six" or "three point one four" */
+pub fn parse_number_words(s: &str) -> Option<f64>
+{
+    let s = s.to_lowercase().replace('-', " ");
+    let words: Vec<&str> = s.split_whitespace().filter(|w| *w != "and").collect();
+
+    if words.is_empty() {
+        return None;
+    }
+
+    let mut total = 0.0;
+    let mut current = 0.0;
+    let mut negative = false;
+    let mut found = false;
+
+    for (i, word) in words.iter().enumerate() {
+        if i == 0 && (*word == "minus" || *word == "negative") {
+            negative = true;
+            continue;
+        }
+
+        if *word == "point" {
+            let mut fraction = String::new();
+
+            for digit in &words[i + 1..] {
+                fraction.push_str(&SMALL_NUMBERS[..10].iter().position(|n| n == digit)?.to_string());
+            }
+
+            if !found || fraction.is_empty() {
+                return None;
+            }
+
+            current += ("0.".to_string() + &fraction).parse::<f64>().ok()?;
+            break;
+        }
+
+        if let Some(n) = SMALL_NUMBERS.iter().position(|n| n == word) {
+            current += n as f64;
+        } else if let Some(n) = TENS.iter().position(|n| !n.is_empty() && n == word) {
+            current += (n * 10) as f64;
+        } else if *word == "hundred" {
+            current = if current == 0.0 { 100.0 } else { current * 100.0 };
+        } else if let Some(&(_, scale)) = SCALES.iter().find(|&&(name, _)| name == *word) {
+            total += if current == 0.0 { scale } else { current * scale };
+            current = 0.0;
+        } else if *word == "a" && i + 1 < words.len() {
+            // "a hundred", "a million"
+            continue;
+        } else if let Some(n) = parse_digits(word).filter(|_| current == 0.0) {
+            // Mixed forms like "2 million", but not two numbers in a row like "20 6"
+            current = n;
+        } else {
+            return None;
+        }
+
+        found = true;
+    }
+
+    if !found {
+        return None;
+    }
+
+    let value = total + current;
+    Some(if negative { -value } else { value })
+}
+
+/* Parses a number written with digits or words. Only finite numbers are returned. */
+pub fn parse_number(s: &str) -> Option<f64>
+{
+    parse_digits(s).or_else(|| parse_number_words(s)).filter(|n| n.is_finite())
+}
+
+/* Returns the form of unit that's used for comparisons */
+fn normalize_unit(unit: &str) -> String
+{
+    let unit = unit.trim().trim_end_matches('.').to_lowercase();
+
+    match UNIT_ALIASES.iter().find(|&&(alias, _)| alias == unit) {
+        Some(&(_, name)) => name.to_string(),
+        None             => unit,
+    }
+}
+
+/*
+ * Parses a number followed by an optional unit, e.g. "206 bones" or "$20". Returns the number
+ * and the normalized unit, which is empty if there wasn't one.
+ */
+pub fn parse_quantity(s: &str) -> Option<(f64, String)>
+{
+    let s = s.trim();
+    let mut unit = String::new();
+
+    let s = match s.chars().next() {
+        Some(ch) if CURRENCY_SYMBOLS.contains(ch) => {
+            unit.push(ch);
+            &s[ch.len_utf8()..]
+        },
+        _ => s,
+    };
+
+    // Split units that are stuck to the number, as in "5km" or "20%"
+    let mut spaced = String::new();
+    let mut prev_digit = false;
+
+    for ch in s.chars() {
+        if prev_digit && (ch.is_alphabetic() || ch == '%') {
+            spaced.push(' ');
+        }
+
+        prev_digit = ch.is_ascii_digit();
+        spaced.push(ch);
+    }
+
+    let tokens: Vec<&str> = spaced.split_whitespace().collect();
+
+    // The longest run of leading words that makes a number is the number; the rest is the unit
+    for n in (1..tokens.len() + 1).rev() {
+        if let Some(value) = parse_number(&tokens[..n].join(" ")) {
+            let rest = tokens[n..].join(" ");
+
+            if !rest.is_empty() {
+                // Another number isn't a unit
+                if !unit.is_empty() || rest.starts_with(|c: char| c.is_ascii_digit()) {
+                    return None;
+                }
+
+                unit = normalize_unit(&rest);
+            }
+
+            return Some((value, unit));
+        }
+    }
+
+    None
+}
+
+/* Returns true if guess and answer are within tolerance of each other and don't have conflicting units */
+pub fn numbers_match(guess: &str, answer: &str, tolerance: f64) -> bool
+{
+    let (guess, guess_unit) = match parse_quantity(guess) {
+        Some(q) => q,
+        None    => return false,
+    };
+
+    let (answer, answer_unit) = match parse_quantity(answer) {
+        Some(q) => q,
+        None    => return false,
+    };
+
+    if !guess_unit.is_empty() && !answer_unit.is_empty() && guess_unit != answer_unit {
+        return false;
+    }
+
+    (guess - answer).abs() <= tolerance + 1e-9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digits() {
+        assert_eq!(parse_digits("206"), Some(206.0));
+        assert_eq!(parse_digits(" 206. "), Some(206.0));
+        assert_eq!(parse_digits("1,000,000"), Some(1e6));
+        assert_eq!(parse_digits("1_000"), Some(1000.0));
+        assert_eq!(parse_digits("-3.5"), Some(-3.5));
+        assert_eq!(parse_digits("20 6"), None);
+        assert_eq!(parse_digits(""), None);
+        assert_eq!(parse_digits("abc"), None);
+    }
+
+    #[test]
+    fn digits_not_finite() {
+        assert_eq!(parse_digits("nan"), None);
+        assert_eq!(parse_digits("NaN"), None);
+        assert_eq!(parse_digits("inf"), None);
+        assert_eq!(parse_digits("-infinity"), None);
+        assert_eq!(parse_digits("1e999"), None);
+        assert_eq!(parse_number("nan"), None);
+        assert_eq!(parse_quantity("inf km"), None);
+    }
+
+    #[test]
+    fn number_words() {
+        assert_eq!(parse_number_words("two hundred and six"), Some(206.0));
+        assert_eq!(parse_number_words("forty-two"), Some(42.0));
+        assert_eq!(parse_number_words("a hundred"), Some(100.0));
+        assert_eq!(parse_number_words("two point five"), Some(2.5));
+        assert_eq!(parse_number_words("minus five"), Some(-5.0));
+        assert_eq!(parse_number_words("one million two hundred thousand"), Some(1.2e6));
+        assert_eq!(parse_number_words("2 million"), Some(2e6));
+        assert_eq!(parse_number_words("20 6"), None);
+        assert_eq!(parse_number_words("twenty 6"), None);
+        assert_eq!(parse_number_words("point five"), None);
+        assert_eq!(parse_number_words("hello"), None);
+    }
+
+    #[test]
+    fn quantities() {
+        assert_eq!(parse_quantity("206"), Some((206.0, String::new())));
+        assert_eq!(parse_quantity("206 bones"), Some((206.0, "bones".to_string())));
+        assert_eq!(parse_quantity("5km"), Some((5.0, "km".to_string())));
+        assert_eq!(parse_quantity("5 kilometres"), Some((5.0, "km".to_string())));
+        assert_eq!(parse_quantity("20%"), Some((20.0, "percent".to_string())));
+        assert_eq!(parse_quantity("$20"), Some((20.0, "$".to_string())));
+        assert_eq!(parse_quantity("two million people"), Some((2e6, "people".to_string())));
+        assert_eq!(parse_quantity("$20 dollars"), None);
+        assert_eq!(parse_quantity("20 6"), None);
+        assert_eq!(parse_quantity("bones"), None);
+    }
+
+    #[test]
+    fn matching() {
+        assert!(numbers_match("206", "206", 0.0));
+        assert!(numbers_match("two hundred and six", "206", 0.0));
+        assert!(numbers_match("5 km", "5km", 0.0));
+        assert!(numbers_match("5", "5km", 0.0));
+        assert!(numbers_match("3.1", "3.14", 0.05));
+        assert!(!numbers_match("3.1", "3.14", 0.01));
+        assert!(!numbers_match("5 mi", "5km", 0.0));
+        assert!(!numbers_match("nan", "5", 1e9));
+        assert!(!numbers_match("20 6", "206", 0.0));
+    }
+}
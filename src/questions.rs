@@ -0,0 +1,847 @@
+/*  questions.rs
+ *
+ *
+ *  Copyright (C) 2015 rustybot All Rights Reserved.
+ *
+ *  This file is part of rustybot.
+ *
+ *  rustybot is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  rustybot is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with rustybot. If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use std::collections::BTreeMap;
+use std::fs::{File, metadata, rename, remove_file};
+use std::io::prelude::*;
+use std::io::{BufReader, BufWriter, SeekFrom};
+use std::str::from_utf8;
+use std::time::UNIX_EPOCH;
+use rand::{Rng, thread_rng};
+use util::*;
+use stats::Difficulty;
+use numbers::{parse_digits, parse_quantity, numbers_match};
+
+pub const QUESTIONS_PATH: &'static str = "data/questions";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AnswerKind {
+    Text,           // Case-insensitive exact match
+    Integer,        // Compared numerically so "42", "42.0", "42." and "forty two" are all accepted
+    Decimal(f64),   // Accepted if within the given distance of the answer
+    Estimate,       // Every player gets one guess and the closest guess wins when time runs out
+    Parts,          // The answer is a list of parts that may be found in any order
+    OrderedParts,   // The answer is a list of parts that must be found in order
+}
+
+// Separates the optional key=value fields that may follow a question's answer
+const FIELD_SEPARATOR: char = '=';
+
+// Separates the options in a choices field
+const CHOICE_SEPARATOR: char = '|';
+
+// Separates the parts of a multi-part answer
+const PART_SEPARATOR: char = '|';
+
+// Letters used to label multiple choice options
+const CHOICE_LETTERS: &'static str = "ABCDEFGHIJ";
+
+// Category given to questions that don't specify one
+pub const DEFAULT_CATEGORY: &'static str = "general";
+
+#[derive(Clone)]
+pub struct Question {
+    pub question: String,
+    pub answer:   String,
+    pub kind:     AnswerKind,
+    pub choices:  Vec<String>,   // Options for multiple choice and true/false questions (empty for free text)
+    pub category: String,        // Always lowercase
+    pub tracked:  bool,          // True if statistics are kept for this question
+    pub retired:  bool,          // True if an admin has taken the question out of play
+    pub difficulty: Option<Difficulty>,  // Difficulty given in the question file, used until we have stats
+}
+
+impl Question {
+    pub fn new(question: &str, answer: &str, kind: AnswerKind) -> Question {
+        Question {
+            question: question.trim().to_string(),
+            answer: answer.trim().to_string(),
+            kind: kind,
+            choices: Vec::new(),
+            category: DEFAULT_CATEGORY.to_string(),
+            tracked: false,
+            retired: false,
+            difficulty: None,
+        }
+    }
+}
+
+// Byte offsets and metadata for every line of the questions file, so large question files don't
+// need to be parsed each time the bot starts
+pub const QUESTIONS_INDEX_PATH: &'static str = "data/questions.idx";
+
+// Identifies an index file. Bump the version when the format changes.
+const INDEX_MAGIC: u32 = 0x52425149;
+const INDEX_VERSION: u32 = 1;
+
+// Number of bytes in the index header.
+// Magic, version, questions file size, questions file modification time, number of categories
+const INDEX_HEADER_SIZE: usize = SIZE_U32 + SIZE_U32 + SIZE_U64 + SIZE_U64 + SIZE_U32;
+
+// Number of bytes in a serialized index entry.
+// Offset, length, category, difficulty, retired
+const INDEX_ENTRY_SIZE: usize = SIZE_U64 + SIZE_U32 + SIZE_U32 + SIZE_U32 + SIZE_U32;
+
+// Returned when the questions file is changed by something other than the bot and hasn't been reloaded yet
+const STALE_INDEX_ERROR: &'static str = "The questions file was changed outside the bot. Try again once it has been reloaded.";
+
+/* Where a question's line is in the questions file, and what we need to know to pick it without reading it */
+#[derive(Clone, Copy)]
+struct QuestionEntry {
+    offset:     u64,
+    length:     u32,                  // Length of the line in bytes, not including the newline
+    category:   u32,                  // Index into QuestionBank::category_names
+    difficulty: Option<Difficulty>,
+    retired:    bool,
+}
+
+/*
+ * Indexes every valid question in the questions file. Only the position and metadata of each
+ * question are kept in memory; the question itself is read from the file when it's needed.
+ * Questions in play are indexed by category; retired questions are not.
+ */
+pub struct QuestionBank {
+    entries:        Vec<QuestionEntry>,             // Sorted by offset
+    category_names: Vec<String>,
+    categories:     BTreeMap<String, Vec<u32>>,     // Category name -> indices into entries
+    file_size:      u64,                            // Size of the questions file the entries describe
+    file_modified:  u64,                            // Modification time of the questions file in nanoseconds
+}
+
+impl QuestionBank {
+    pub fn new() -> QuestionBank {
+        QuestionBank {
+            entries: Vec::new(),
+            category_names: Vec::new(),
+            categories: BTreeMap::new(),
+            file_size: 0,
+            file_modified: 0,
+        }
+    }
+
+    /* Adds the question whose line starts at offset in the questions file */
+    pub fn add(&mut self, question: &Question, offset: u64, length: usize) {
+        let category = match self.category_names.iter().position(|c| *c == question.category) {
+            Some(idx) => idx as u32,
+            None => {
+                self.category_names.push(question.category.to_string());
+                (self.category_names.len() - 1) as u32
+            }
+        };
+
+        self.entries.push(QuestionEntry {
+            offset: offset,
+            length: length as u32,
+            category: category,
+            difficulty: question.difficulty,
+            retired: question.retired,
+        });
+
+        if !question.retired {
+            let idx = (self.entries.len() - 1) as u32;
+            self.categories.entry(question.category.to_string()).or_insert(Vec::new()).push(idx);
+        }
+    }
+
+    /* Returns the number of questions in play */
+    pub fn len(&self) -> usize {
+        self.categories.values().map(|list| list.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /* Returns true if the questions file has changed since it was indexed, leaving our offsets wrong */
+    pub fn is_stale(&self) -> bool {
+        question_file_stamp() != Some((self.file_size, self.file_modified))
+    }
+
+    /* Reads the question for entries[idx] from the questions file. Nothing is read from a stale index. */
+    fn read(&self, idx: usize) -> Option<Question> {
+        // Games ask again every tick until the main loop reloads the file, so this isn't logged
+        if self.is_stale() {
+            return None;
+        }
+
+        let entry = self.entries.get(idx)?;
+        let mut fp = File::open(QUESTIONS_PATH).ok()?;
+        let mut buf = vec![0; entry.length as usize];
+
+        fp.seek(SeekFrom::Start(entry.offset)).ok()?;
+        fp.read_exact(&mut buf).ok()?;
+
+        parse_question(&String::from_utf8_lossy(&buf))
+    }
+
+    /* Returns the question whose text is question, including retired questions. This reads the whole file. */
+    pub fn find(&self, question: &str) -> Option<Question> {
+        let mut found = None;
+
+        let result = for_each_question_line(|_, line| {
+            if found.is_none() && line.split('`').next().map(|q| q.trim()) == Some(question) {
+                found = parse_question(line);
+            }
+        });
+
+        if let Err(e) = result {
+            println!("Failed to search questions: {}", e);
+        }
+
+        found
+    }
+
+    pub fn has_category(&self, name: &str) -> bool {
+        self.categories.contains_key(name)
+    }
+
+    /* Returns every category name along with its number of questions, in alphabetical order */
+    pub fn categories(&self) -> Vec<(&str, usize)> {
+        self.categories.iter().map(|(name, list)| (name.as_str(), list.len())).collect()
+    }
+
+    /* Returns the number of questions in the given categories. An empty list means all categories. */
+    pub fn count(&self, categories: &Vec<String>) -> usize {
+        if categories.is_empty() {
+            return self.len();
+        }
+
+        categories.iter().filter_map(|c| self.categories.get(c)).map(|list| list.len()).sum()
+    }
+
+    /* Returns a random question from the given categories. An empty list means all categories. */
+    pub fn random(&self, categories: &Vec<String>) -> Option<Question> {
+        let total = self.count(categories);
+
+        if total == 0 {
+            return None;
+        }
+
+        let mut rng = thread_rng();
+        let mut n = rng.gen_range(0, total);
+
+        let lists: Vec<&Vec<u32>> = if categories.is_empty() {
+            self.categories.values().collect()
+        } else {
+            categories.iter().filter_map(|c| self.categories.get(c)).collect()
+        };
+
+        for list in lists {
+            if n < list.len() {
+                return self.read(list[n] as usize);
+            }
+
+            n -= list.len();
+        }
+
+        None
+    }
+
+    /* Appends a question line to the questions file and puts it into play */
+    pub fn append(&mut self, line: &str) -> Result<(), String> {
+        if self.is_stale() {
+            return Err(STALE_INDEX_ERROR.to_string());
+        }
+
+        let question = match parse_question(line) {
+            Some(question) => question,
+            None           => return Err("The question is invalid".to_string()),
+        };
+
+        let offset = append_question_file(line)?;
+        self.add(&question, offset, line.len());
+        self.save_index();
+        Ok(())
+    }
+
+    /*
+     * Finds the line for question in the questions file and passes its fields to edit, then writes
+     * the file back and updates the index. The edited line must still parse as a valid question.
+     */
+    pub fn edit<F>(&mut self, question: &str, edit: F) -> Result<(), String>
+        where F: Fn(&mut Vec<String>)
+    {
+        if self.is_stale() {
+            return Err(STALE_INDEX_ERROR.to_string());
+        }
+
+        let (offset, old_length, line) = edit_question_file(question, edit)?;
+        let edited = parse_question(&line).unwrap();
+        let delta = line.len() as i64 - old_length as i64;
+
+        if let Ok(idx) = self.entries.binary_search_by_key(&offset, |e| e.offset) {
+            self.entries.remove(idx);
+            let category = edited.category.to_string();
+
+            let mut entry = QuestionEntry {
+                offset: offset,
+                length: line.len() as u32,
+                category: 0,
+                difficulty: edited.difficulty,
+                retired: edited.retired,
+            };
+
+            entry.category = match self.category_names.iter().position(|c| *c == category) {
+                Some(idx) => idx as u32,
+                None => {
+                    self.category_names.push(category);
+                    (self.category_names.len() - 1) as u32
+                }
+            };
+
+            self.entries.insert(idx, entry);
+        }
+
+        for entry in self.entries.iter_mut().filter(|e| e.offset > offset) {
+            entry.offset = (entry.offset as i64 + delta) as u64;
+        }
+
+        self.rebuild_categories();
+        self.save_index();
+        Ok(())
+    }
+
+    fn rebuild_categories(&mut self) {
+        self.categories.clear();
+
+        for (idx, entry) in self.entries.iter().enumerate() {
+            if !entry.retired {
+                let name = self.category_names[entry.category as usize].to_string();
+                self.categories.entry(name).or_insert(Vec::new()).push(idx as u32);
+            }
+        }
+    }
+
+    /* Remembers the current size and modification time of the questions file so a stale index can be detected */
+    pub fn set_file_stamp(&mut self, stamp: (u64, u64)) {
+        self.file_size = stamp.0;
+        self.file_modified = stamp.1;
+    }
+
+    /* Writes the index to disk, stamped with the current state of the questions file */
+    pub fn save_index(&mut self) {
+        if let Some(stamp) = question_file_stamp() {
+            self.set_file_stamp(stamp);
+        }
+
+        let mut data: Vec<u8> = Vec::with_capacity(INDEX_HEADER_SIZE + self.entries.len() * INDEX_ENTRY_SIZE);
+
+        u32_to_bytes_le(INDEX_MAGIC, &mut data);
+        u32_to_bytes_le(INDEX_VERSION, &mut data);
+        u64_to_bytes_le(self.file_size, &mut data);
+        u64_to_bytes_le(self.file_modified, &mut data);
+        u32_to_bytes_le(self.category_names.len() as u32, &mut data);
+
+        for name in &self.category_names {
+            u32_to_bytes_le(name.len() as u32, &mut data);
+            data.extend_from_slice(name.as_bytes());
+        }
+
+        u32_to_bytes_le(self.entries.len() as u32, &mut data);
+
+        for entry in &self.entries {
+            u64_to_bytes_le(entry.offset, &mut data);
+            u32_to_bytes_le(entry.length, &mut data);
+            u32_to_bytes_le(entry.category, &mut data);
+            u32_to_bytes_le(difficulty_to_u32(entry.difficulty), &mut data);
+            u32_to_bytes_le(entry.retired as u32, &mut data);
+        }
+
+        if let Err(e) = save_data(QUESTIONS_INDEX_PATH, &data) {
+            println!("Failed to save question index: {}", e);
+        }
+    }
+
+    /* Loads the index from disk. Returns None if there's no index or it doesn't match the questions file. */
+    pub fn load_index() -> Option<QuestionBank> {
+        let mut buf = Vec::new();
+        let mut fp = File::open(QUESTIONS_INDEX_PATH).ok()?;
+        fp.read_to_end(&mut buf).ok()?;
+
+        if buf.len() < INDEX_HEADER_SIZE {
+            return None;
+        }
+
+        let (file_size, file_modified) = question_file_stamp()?;
+
+        if bytes_le_to_u32(&buf[0..4]) != INDEX_MAGIC || bytes_le_to_u32(&buf[4..8]) != INDEX_VERSION
+           || bytes_le_to_u64(&buf[8..16]) != file_size || bytes_le_to_u64(&buf[16..24]) != file_modified {
+            return None;
+        }
+
+        let mut bank = QuestionBank::new();
+        bank.set_file_stamp((file_size, file_modified));
+
+        let num_categories = bytes_le_to_u32(&buf[24..28]);
+        let mut pos = INDEX_HEADER_SIZE;
+
+        for _ in 0..num_categories {
+            let len = bytes_le_to_u32(buf.get(pos..pos + SIZE_U32)?) as usize;
+            pos += SIZE_U32;
+            let name = from_utf8(buf.get(pos..pos + len)?).ok()?;
+            bank.category_names.push(name.to_string());
+            pos += len;
+        }
+
+        let num_entries = bytes_le_to_u32(buf.get(pos..pos + SIZE_U32)?) as usize;
+        pos += SIZE_U32;
+
+        if buf.len() - pos != num_entries * INDEX_ENTRY_SIZE {
+            return None;
+        }
+
+        for chunk in buf[pos..].chunks(INDEX_ENTRY_SIZE) {
+            let entry = QuestionEntry {
+                offset: bytes_le_to_u64(&chunk[0..8]),
+                length: bytes_le_to_u32(&chunk[8..12]),
+                category: bytes_le_to_u32(&chunk[12..16]),
+                difficulty: difficulty_from_u32(bytes_le_to_u32(&chunk[16..20])),
+                retired: bytes_le_to_u32(&chunk[20..24]) != 0,
+            };
+
+            if entry.category as usize >= bank.category_names.len() {
+                return None;
+            }
+
+            bank.entries.push(entry);
+        }
+
+        bank.rebuild_categories();
+        Some(bank)
+    }
+}
+
+fn difficulty_to_u32(difficulty: Option<Difficulty>) -> u32
+{
+    match difficulty {
+        None                     => 0,
+        Some(Difficulty::Easy)   => 1,
+        Some(Difficulty::Medium) => 2,
+        Some(Difficulty::Hard)   => 3,
+    }
+}
+
+fn difficulty_from_u32(n: u32) -> Option<Difficulty>
+{
+    match n {
+        1 => Some(Difficulty::Easy),
+        2 => Some(Difficulty::Medium),
+        3 => Some(Difficulty::Hard),
+        _ => None,
+    }
+}
+
+/* Returns the size and modification time in nanoseconds of the questions file */
+pub fn question_file_stamp() -> Option<(u64, u64)>
+{
+    let meta = metadata(QUESTIONS_PATH).ok()?;
+    let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+
+    Some((meta.len(), modified.as_secs() * 1_000_000_000 + modified.subsec_nanos() as u64))
+}
+
+/*
+ * Calls f with the byte offset and contents of each line in the questions file, without reading
+ * the whole file into memory. The newline is not included.
+ */
+pub fn for_each_question_line<F>(mut f: F) -> Result<(), String>
+    where F: FnMut(u64, &str)
+{
+    let fp = File::open(QUESTIONS_PATH).map_err(|e| format!("Open failed on file {}: {}", QUESTIONS_PATH, e))?;
+    let mut reader = BufReader::new(fp);
+    let mut buf = Vec::new();
+    let mut offset = 0;
+
+    loop {
+        buf.clear();
+
+        let size = reader.read_until(b'\n', &mut buf)
+                         .map_err(|e| format!("Read failed on file {}: {}", QUESTIONS_PATH, e))?;
+
+        if size == 0 {
+            break;
+        }
+
+        let line = if buf.ends_with(b"\n") { &buf[..size - 1] } else { &buf[..] };
+        f(offset, &String::from_utf8_lossy(line));
+        offset += size as u64;
+    }
+
+    Ok(())
+}
+
+/*
+ * Parses a line from the questions file. Lines are of the form:
+ *
+ *     question`answer[`key=value...]
+ *
+ * Optional fields are category=name, difficulty=easy|medium|hard, choices=a|b|c which makes the
+ * question multiple choice, and retired=yes which takes the question out of play. One of the
+ * choices must be the answer. type=text|integer|decimal|estimate|parts|ordered sets how answers
+ * are checked, and tolerance=n or tolerance=n% how far off a decimal answer may be. Answers of
+ * the parts and ordered types are lists separated by |. Answers that are plain
+ * numbers are checked as numbers unless the type says otherwise. Returns None if the line is
+ * malformed.
+ */
+pub fn parse_question(line: &str) -> Option<Question>
+{
+    check_question(line).ok()
+}
+
+/* Like parse_question, but returns the reason a malformed line was rejected */
+pub fn check_question(line: &str) -> Result<Question, String>
+{
+    let fields: Vec<&str> = line.split('`').collect();
+
+    if fields.len() < 2 {
+        return Err("no ` between question and answer".to_string());
+    }
+
+    let mut question = Question::new(fields[0], fields[1], AnswerKind::Text);
+    question.tracked = true;
+
+    let mut kind = None;
+    let mut tolerance = None;
+
+    if question.question.is_empty() {
+        return Err("empty question".to_string());
+    }
+
+    if question.answer.is_empty() {
+        return Err("empty answer".to_string());
+    }
+
+    for field in &fields[2..] {
+        if field.trim().is_empty() {
+            continue;
+        }
+
+        // A field that isn't key=value is almost always the rest of an answer that contains a `
+        let (key, value) = match field.find(FIELD_SEPARATOR) {
+            Some(idx) => (field[..idx].trim(), field[idx + 1..].trim()),
+            None      => return Err(format!("answer contains the ` character (\"{}\" is not a field)", field.trim())),
+        };
+
+        match key {
+            "choices" => {
+                question.choices = value.split(CHOICE_SEPARATOR)
+                                        .map(|c| c.trim().to_string())
+                                        .filter(|c| !c.is_empty())
+                                        .collect();
+            },
+            "category" => {
+                if !value.is_empty() {
+                    question.category = value.to_lowercase();
+                }
+            },
+            "difficulty" => {
+                question.difficulty = match Difficulty::from_name(&value.to_lowercase()) {
+                    Some(d) => Some(d),
+                    None    => return Err(format!("unknown difficulty \"{}\"", value)),
+                };
+            },
+            "retired" => question.retired = value == "yes",
+            "type" => kind = Some(value.to_lowercase()),
+            "tolerance" => tolerance = Some(value.to_string()),
+            _ => return Err(format!("unknown field \"{}\"", key)),
+        }
+    }
+
+    question.kind = answer_kind(&question.answer, kind, tolerance)?;
+
+    if !question.choices.is_empty() {
+        if question.choices.len() < 2 || question.choices.len() > CHOICE_LETTERS.len() {
+            return Err(format!("multiple choice questions need 2 to {} choices", CHOICE_LETTERS.len()));
+        }
+
+        let answer = question.answer.to_lowercase();
+
+        if !question.choices.iter().any(|c| c.to_lowercase() == answer) {
+            return Err("the answer is not one of the choices".to_string());
+        }
+    }
+
+    Ok(question)
+}
+
+/*
+ * Finds the line for question in the questions file and passes its fields to edit, then writes
+ * the file back. The edited line must still parse as a valid question. Returns the offset and
+ * length of the old line along with the new one.
+ */
+fn edit_question_file<F>(question: &str, edit: F) -> Result<(u64, usize, String), String>
+    where F: Fn(&mut Vec<String>)
+{
+    let temp_path = format!("{}.tmp", QUESTIONS_PATH);
+    let temp = File::create(&temp_path).map_err(|e| format!("Couldn't open file {}: {}", temp_path, e))?;
+    let mut writer = BufWriter::new(temp);
+    let mut result = Err("Question not found in question file".to_string());
+    let mut matched = false;
+    let mut write_error = None;
+
+    for_each_question_line(|offset, line| {
+        let mut line = line.to_string();
+
+        if !matched {
+            let mut fields: Vec<String> = line.split('`').map(|f| f.to_string()).collect();
+
+            if fields[0].trim() == question {
+                matched = true;
+                edit(&mut fields);
+                let edited = fields.join("`");
+
+                if parse_question(&edited).is_some() {
+                    result = Ok((offset, line.len(), edited.to_string()));
+                    line = edited;
+                } else {
+                    result = Err("The edited question is invalid".to_string());
+                }
+            }
+        }
+
+        if let Err(e) = writer.write_all(line.as_bytes()).and_then(|_| writer.write_all(b"\n")) {
+            write_error = Some(format!("Write failed on file {}: {}", temp_path, e));
+        }
+    })?;
+
+    if let Some(e) = write_error {
+        return Err(e);
+    }
+
+    writer.flush().map_err(|e| format!("Write failed on file {}: {}", temp_path, e))?;
+
+    if result.is_ok() {
+        rename(&temp_path, QUESTIONS_PATH).map_err(|e| format!("Couldn't replace {}: {}", QUESTIONS_PATH, e))?;
+    } else {
+        let _ = remove_file(&temp_path);
+    }
+
+    result
+}
+
+/* Appends a question line to the end of the questions file. Returns the offset the line was written at. */
+pub fn append_question_file(line: &str) -> Result<u64, String>
+{
+    let mut fp = match open_file(QUESTIONS_PATH, true) {
+        Some(fp) => fp,
+        None     => return Err(format!("Failed to open file {}", QUESTIONS_PATH)),
+    };
+
+    let size = fp.seek(SeekFrom::End(0)).map_err(|e| format!("Seek failed on file {}: {}", QUESTIONS_PATH, e))?;
+    let mut data = String::new();
+
+    if size > 0 {
+        let mut last = [0; 1];
+        fp.seek(SeekFrom::End(-1)).and_then(|_| fp.read_exact(&mut last))
+          .map_err(|e| format!("Read failed on file {}: {}", QUESTIONS_PATH, e))?;
+
+        if last[0] != b'\n' {
+            data.push('\n');
+        }
+    }
+
+    data.push_str(line);
+    data.push('\n');
+
+    fp.write_all(data.as_bytes()).map_err(|e| format!("Write failed on file {}: {}", QUESTIONS_PATH, e))?;
+    Ok(size + data.len() as u64 - line.len() as u64 - 1)
+}
+
+/* Returns the choices formatted as lettered options, e.g. "A) Paris  B) London" */
+pub fn format_choices(choices: &Vec<String>) -> String
+{
+    let options: Vec<String> = choices.iter()
+                                      .zip(CHOICE_LETTERS.chars())
+                                      .map(|(c, letter)| format!("{}) {}", letter, c))
+                                      .collect();
+    options.join("  ")
+}
+
+/*
+ * Returns the choice a message picks, either by its letter or by its full text.
+ * Returns None if the message isn't an attempt at answering.
+ */
+pub fn get_choice<'a>(choices: &'a Vec<String>, message: &str) -> Option<&'a str>
+{
+    let message = message.trim();
+
+    if message.chars().count() == 1 {
+        let letter = message.to_uppercase();
+        let idx = CHOICE_LETTERS.find(letter.as_str())?;
+        return choices.get(idx).map(|c| c.as_str());
+    }
+
+    let message = message.to_lowercase();
+    choices.iter().find(|c| c.to_lowercase() == message).map(|c| c.as_str())
+}
+
+/* Works out how answers to a question are checked from its type and tolerance fields */
+fn answer_kind(answer: &str, kind: Option<String>, tolerance: Option<String>) -> Result<AnswerKind, String>
+{
+    let value = parse_quantity(answer).map(|(n, _)| n);
+
+    let tolerance = match tolerance {
+        Some(t) => {
+            let answer = value.ok_or("tolerance given but the answer is not a number".to_string())?;
+
+            let t = if t.ends_with('%') {
+                parse_digits(t.trim_end_matches('%')).map(|p| (answer * p / 100.0).abs())
+            } else {
+                parse_digits(&t)
+            };
+
+            match t {
+                Some(t) if t >= 0.0 => Some(t),
+                _ => return Err("tolerance must be a positive number or percentage".to_string()),
+            }
+        },
+        None => None,
+    };
+
+    let kind = match kind {
+        Some(kind) => kind,
+        None if tolerance.is_some() => "decimal".to_string(),
+        None => {
+            // Plain numbers are checked numerically; answers with words in them stay text
+            return Ok(match parse_digits(answer) {
+                Some(n) if n.fract() == 0.0 => AnswerKind::Integer,
+                Some(_)                     => AnswerKind::Decimal(0.0),
+                None                        => AnswerKind::Text,
+            });
+        },
+    };
+
+    if kind == "parts" || kind == "ordered" {
+        if answer_parts(answer).len() < 2 {
+            return Err(format!("{} answers need at least two parts separated by {}", kind, PART_SEPARATOR));
+        }
+
+        return Ok(if kind == "parts" { AnswerKind::Parts } else { AnswerKind::OrderedParts });
+    }
+
+    if kind != "text" && value.is_none() {
+        return Err(format!("answer is not a number but the type is {}", kind));
+    }
+
+    match kind.as_str() {
+        "text"     => Ok(AnswerKind::Text),
+        "integer"  => Ok(tolerance.map(AnswerKind::Decimal).unwrap_or(AnswerKind::Integer)),
+        "decimal"  => Ok(AnswerKind::Decimal(tolerance.unwrap_or(0.0))),
+        "estimate" => Ok(AnswerKind::Estimate),
+        _          => Err(format!("unknown type \"{}\"", kind)),
+    }
+}
+
+/* Returns the parts of a multi-part answer */
+pub fn answer_parts(answer: &str) -> Vec<String>
+{
+    answer.split(PART_SEPARATOR).map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect()
+}
+
+/* Returns true if guess matches one part of a multi-part answer. Parts that are plain numbers are compared numerically. */
+pub fn check_part(guess: &str, part: &str) -> bool
+{
+    match answer_kind(part, None, None) {
+        Ok(kind) => check_answer(guess, part, kind),
+        Err(_)   => false,
+    }
+}
+
+/*
+ * Returns true if guess is a correct answer. Estimates are never simply correct; the closest
+ * guess wins. Multi-part answers are checked a part at a time with check_part.
+ */
+pub fn check_answer(guess: &str, answer: &str, kind: AnswerKind) -> bool
+{
+    match kind {
+        AnswerKind::Text               => guess.trim().to_lowercase() == answer.to_lowercase(),
+        AnswerKind::Integer            => numbers_match(guess, answer, 0.0),
+        AnswerKind::Decimal(tolerance) => numbers_match(guess, answer, tolerance),
+        AnswerKind::Estimate           => false,
+        AnswerKind::Parts              => false,
+        AnswerKind::OrderedParts       => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(answer: &str, kind: Option<&str>, tolerance: Option<&str>) -> Result<AnswerKind, String> {
+        answer_kind(answer, kind.map(|k| k.to_string()), tolerance.map(|t| t.to_string()))
+    }
+
+    #[test]
+    fn plain_answers() {
+        assert_eq!(kind("Paris", None, None), Ok(AnswerKind::Text));
+        assert_eq!(kind("42", None, None), Ok(AnswerKind::Integer));
+        assert_eq!(kind("1,000", None, None), Ok(AnswerKind::Integer));
+        assert_eq!(kind("3.14", None, None), Ok(AnswerKind::Decimal(0.0)));
+        assert_eq!(kind("206 bones", None, None), Ok(AnswerKind::Text));
+        assert_eq!(kind("nan", None, None), Ok(AnswerKind::Text));
+        assert_eq!(kind("1984 20", None, None), Ok(AnswerKind::Text));
+    }
+
+    #[test]
+    fn typed_answers() {
+        assert_eq!(kind("42", Some("text"), None), Ok(AnswerKind::Text));
+        assert_eq!(kind("8849 m", Some("estimate"), None), Ok(AnswerKind::Estimate));
+        assert_eq!(kind("3.14", Some("decimal"), None), Ok(AnswerKind::Decimal(0.0)));
+        assert_eq!(kind("red|green|blue", Some("parts"), None), Ok(AnswerKind::Parts));
+        assert_eq!(kind("red|green|blue", Some("ordered"), None), Ok(AnswerKind::OrderedParts));
+        assert!(kind("red", Some("parts"), None).is_err());
+        assert!(kind("Paris", Some("integer"), None).is_err());
+        assert!(kind("42", Some("fraction"), None).is_err());
+    }
+
+    #[test]
+    fn tolerances() {
+        assert_eq!(kind("100", None, Some("5")), Ok(AnswerKind::Decimal(5.0)));
+        assert_eq!(kind("200", None, Some("10%")), Ok(AnswerKind::Decimal(20.0)));
+        assert_eq!(kind("100", Some("integer"), Some("2")), Ok(AnswerKind::Decimal(2.0)));
+        assert!(kind("Paris", None, Some("5")).is_err());
+        assert!(kind("100", None, Some("-5")).is_err());
+        assert!(kind("100", None, Some("nan")).is_err());
+        assert!(kind("100", None, Some("inf")).is_err());
+    }
+
+    #[test]
+    fn question_lines() {
+        let q = check_question("Capital of France?`Paris`category=Geography`difficulty=easy").unwrap();
+        assert_eq!(q.question, "Capital of France?");
+        assert_eq!(q.answer, "Paris");
+        assert_eq!(q.category, "geography");
+        assert_eq!(q.kind, AnswerKind::Text);
+
+        let q = check_question("Pick one`b`choices=a|b|c").unwrap();
+        assert_eq!(q.choices, vec!["a", "b", "c"]);
+
+        assert!(check_question("No answer").is_err());
+        assert!(check_question("`answer").is_err());
+        assert!(check_question("Question?`").is_err());
+        assert!(check_question("Question?`an`swer").is_err());
+        assert!(check_question("Question?`answer`colour=red").is_err());
+        assert!(check_question("Question?`answer`difficulty=hardest").is_err());
+        assert!(check_question("Pick one`d`choices=a|b|c").is_err());
+        assert!(check_question("Pick one`a`choices=a").is_err());
+    }
+}
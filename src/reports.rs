@@ -0,0 +1,156 @@
+/*  reports.rs
+ *
+ *
+ *  Copyright (C) 2015 rustybot All Rights Reserved.
+ *
+ *  This file is part of rustybot.
+ *
+ *  rustybot is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  rustybot is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with rustybot. If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::fmt::Write;
+use util::*;
+
+const REPORTS_PATH: &'static str = "data/reports";
+
+pub struct Report {
+    pub id:           u32,
+    pub question:     String,   // Text of the reported question
+    pub reporter_pk:  String,
+    pub reporter:     String,   // Nick of the reporter at the time of the report
+    pub reason:       String,
+}
+
+/* Questions players have reported as wrong or outdated, waiting for an admin to review them */
+pub struct ReportQueue {
+    reports: Vec<Report>,
+    next_id: u32,
+}
+
+impl ReportQueue {
+    pub fn new() -> ReportQueue {
+        ReportQueue {
+            reports: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    pub fn list(&self) -> &Vec<Report> {
+        &self.reports
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Report> {
+        self.reports.iter().find(|r| r.id == id)
+    }
+
+    /* Adds a report and returns its id, or None if the reporter has already reported this question */
+    pub fn add(&mut self, question: &str, reporter_pk: &str, reporter: &str, reason: &str) -> Option<u32> {
+        if self.reports.iter().any(|r| r.question == question && r.reporter_pk == reporter_pk) {
+            return None;
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        // Backticks and newlines would break the file format
+        let clean = |s: &str| s.replace('`', "'").replace('\n', " ");
+
+        self.reports.push(Report {
+            id: id,
+            question: question.to_string(),
+            reporter_pk: reporter_pk.to_string(),
+            reporter: clean(reporter),
+            reason: clean(reason),
+        });
+
+        self.save();
+        Some(id)
+    }
+
+    /* Removes report id. Returns false if there's no such report. */
+    pub fn dismiss(&mut self, id: u32) -> bool {
+        let len = self.reports.len();
+        self.reports.retain(|r| r.id != id);
+
+        if self.reports.len() == len {
+            return false;
+        }
+
+        self.save();
+        true
+    }
+
+    /* Removes every report for question */
+    pub fn resolve(&mut self, question: &str) {
+        self.reports.retain(|r| r.question != question);
+        self.save();
+    }
+
+    /* Saves reports as one line each: id`question`reporter_pk`reporter`reason */
+    pub fn save(&self) {
+        let mut data = String::new();
+
+        for r in &self.reports {
+            write!(&mut data, "{}`{}`{}`{}`{}\n", r.id, r.question, r.reporter_pk, r.reporter, r.reason).unwrap();
+        }
+
+        match save_data(REPORTS_PATH, &data.into_bytes()) {
+            Ok(_) => (),
+            Err(e) => println!("save_data failed: {}", e),
+        }
+    }
+
+    pub fn load(&mut self) {
+        let fp = match open_file(REPORTS_PATH, true) {
+            Some(fp) => fp,
+            None     => return,
+        };
+
+        let mut data = String::new();
+        let mut reader = BufReader::new(&fp);
+
+        match reader.read_to_string(&mut data) {
+            Ok(_)  => (),
+            Err(e) => return println!("Failed to read reports: {}", e),
+        };
+
+        for line in data.lines() {
+            let fields: Vec<&str> = line.split('`').collect();
+
+            if fields.len() != 5 {
+                continue;
+            }
+
+            let id = match fields[0].parse::<u32>() {
+                Ok(id) => id,
+                Err(_) => continue,
+            };
+
+            self.next_id = self.next_id.max(id + 1);
+
+            self.reports.push(Report {
+                id: id,
+                question: fields[1].to_string(),
+                reporter_pk: fields[2].to_string(),
+                reporter: fields[3].to_string(),
+                reason: fields[4].to_string(),
+            });
+        }
+
+        println!("Loaded {} question reports", self.reports.len());
+    }
+}
@@ -0,0 +1,86 @@
+/*  scramble.rs
+ *
+ *
+ *  Copyright (C) 2015 rustybot All Rights Reserved.
+ *
+ *  This file is part of rustybot.
+ *
+ *  rustybot is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  rustybot is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with rustybot. If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use rand::seq::SliceRandom;
+use rand::{Rng, thread_rng};
+use game::{GameMode, GameContext};
+use trivia::{Trivia, QuestionSource};
+use questions::{Question, AnswerKind};
+
+// Number of times we try to produce a scramble that differs from the original word
+const MAX_SHUFFLE_TRIES: usize = 10;
+
+/* Shuffles the letters of each word in s, leaving spaces in place. */
+fn scramble_word(s: &str) -> String
+{
+    let mut rng = thread_rng();
+    let mut scrambled = Vec::new();
+
+    for word in s.split_whitespace() {
+        let mut chars: Vec<char> = word.chars().collect();
+        chars.shuffle(&mut rng);
+        scrambled.push(chars.into_iter().collect::<String>());
+    }
+
+    scrambled.join(" ")
+}
+
+/* Picks words at random from the word list and shuffles their letters */
+pub struct Scramble;
+
+impl QuestionSource for Scramble {
+    fn is_empty(&self, ctx: &GameContext) -> bool {
+        ctx.words.is_empty()
+    }
+
+    fn next_question(&mut self, ctx: &GameContext) -> Option<Question> {
+        if ctx.words.is_empty() {
+            return None;
+        }
+
+        let mut rng = thread_rng();
+        let answer = ctx.words[rng.gen_range(0, ctx.words.len())].trim().to_string();
+
+        if answer.is_empty() {
+            return None;
+        }
+
+        let lowercase = answer.to_lowercase();
+        let mut scrambled = scramble_word(&lowercase);
+
+        for _ in 0..MAX_SHUFFLE_TRIES {
+            if scrambled != lowercase {
+                break;
+            }
+
+            scrambled = scramble_word(&lowercase);
+        }
+
+        let question = format!("Unscramble: {}", scrambled.to_uppercase());
+        Some(Question::new(&question, &answer, AnswerKind::Text))
+    }
+}
+
+pub fn new_scramble() -> Box<dyn GameMode>
+{
+    Box::new(Trivia::new("scramble", "Scramble time!", Box::new(Scramble)))
+}
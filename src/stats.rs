@@ -0,0 +1,238 @@
+/*  stats.rs
+ *
+ *
+ *  Copyright (C) 2015 rustybot All Rights Reserved.
+ *
+ *  This file is part of rustybot.
+ *
+ *  rustybot is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  rustybot is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with rustybot. If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::collections::HashMap;
+use std::fmt::Write;
+use util::*;
+use questions::Question;
+
+const QUESTION_STATS_PATH: &'static str = "data/qstats";
+
+// Number of times a question must be asked before its difficulty is trusted
+const MIN_TIMES_ASKED: u32 = 5;
+
+// Difficulty given to questions we don't know enough about
+const DEFAULT_DIFFICULTY: f64 = 0.5;
+
+// How much the average answer time counts towards difficulty compared to the answer rate
+const ANSWER_TIME_WEIGHT: f64 = 0.25;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        match name {
+            "easy"   => Some(Difficulty::Easy),
+            "medium" => Some(Difficulty::Medium),
+            "hard"   => Some(Difficulty::Hard),
+            _        => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Difficulty::Easy   => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard   => "hard",
+        }
+    }
+
+    /* Returns the difficulty level for an empirical difficulty value between 0 and 1 */
+    pub fn from_value(value: f64) -> Difficulty {
+        if value < 0.4 {
+            Difficulty::Easy
+        } else if value < 0.75 {
+            Difficulty::Medium
+        } else {
+            Difficulty::Hard
+        }
+    }
+
+    /* Returns the empirical difficulty value typical of this level */
+    pub fn value(&self) -> f64 {
+        match *self {
+            Difficulty::Easy   => 0.2,
+            Difficulty::Medium => 0.55,
+            Difficulty::Hard   => 0.85,
+        }
+    }
+}
+
+pub struct QuestionStats {
+    pub asked:       u32,   // Number of rounds the question has been asked in
+    pub answered:    u32,   // Number of those rounds that were won
+    pub answer_time: u64,   // Total milliseconds taken by the winners
+    pub hints:       u32,   // Total hints given
+    pub skipped:     u32,   // Number of rounds players voted to skip it
+}
+
+impl QuestionStats {
+    pub fn new() -> QuestionStats {
+        QuestionStats {
+            asked: 0,
+            answered: 0,
+            answer_time: 0,
+            hints: 0,
+            skipped: 0,
+        }
+    }
+
+    /* Returns the average number of seconds it took to answer the question */
+    pub fn average_answer_time(&self) -> Option<f64> {
+        if self.answered == 0 {
+            return None;
+        }
+
+        Some(self.answer_time as f64 / self.answered as f64 / 1000.0)
+    }
+
+    /*
+     * Returns a value between 0 (always answered right away) and 1 (never answered) based on how
+     * often and how quickly the question is answered, or None if it hasn't been asked enough.
+     */
+    pub fn difficulty(&self, time_limit: i64) -> Option<f64> {
+        if self.asked < MIN_TIMES_ASKED {
+            return None;
+        }
+
+        let miss_rate = 1.0 - (self.answered as f64 / self.asked as f64);
+
+        let slowness = match self.average_answer_time() {
+            Some(t) => (t / time_limit as f64).min(1.0),
+            None    => 1.0,
+        };
+
+        Some((miss_rate * (1.0 - ANSWER_TIME_WEIGHT) + slowness * ANSWER_TIME_WEIGHT).max(0.0).min(1.0))
+    }
+}
+
+/* Statistics for every question that has been asked, keyed by question text */
+pub struct QuestionStatsDB {
+    hashmap: HashMap<String, QuestionStats>,
+}
+
+impl QuestionStatsDB {
+    pub fn new() -> QuestionStatsDB {
+        QuestionStatsDB { hashmap: HashMap::new() }
+    }
+
+    pub fn get(&self, question: &str) -> Option<&QuestionStats> {
+        self.hashmap.get(question)
+    }
+
+    /*
+     * Returns the difficulty of question. If it hasn't been asked enough we go by the difficulty
+     * given in the question file, or failing that assume medium difficulty.
+     */
+    pub fn difficulty(&self, question: &Question, time_limit: i64) -> f64 {
+        if let Some(d) = self.hashmap.get(&question.question).and_then(|s| s.difficulty(time_limit)) {
+            return d;
+        }
+
+        match question.difficulty {
+            Some(d) => d.value(),
+            None    => DEFAULT_DIFFICULTY,
+        }
+    }
+
+    /* Records a round for question. answer_time is None if nobody answered it. */
+    pub fn record_round(&mut self, question: &str, answer_time: Option<u64>, hints: u32) {
+        let stats = self.hashmap.entry(question.to_string()).or_insert(QuestionStats::new());
+        stats.asked += 1;
+        stats.hints += hints;
+
+        if let Some(t) = answer_time {
+            stats.answered += 1;
+            stats.answer_time += t;
+        }
+    }
+
+    /* Records a round for question that players voted to skip. It counts as unanswered. */
+    pub fn record_skip(&mut self, question: &str, hints: u32) {
+        self.record_round(question, None, hints);
+
+        if let Some(stats) = self.hashmap.get_mut(question) {
+            stats.skipped += 1;
+        }
+    }
+
+    /* Saves stats as one line per question: question`asked`answered`answer_time`hints`skipped */
+    pub fn save(&self) {
+        if self.hashmap.is_empty() {
+            return;
+        }
+
+        let mut data = String::new();
+
+        for (question, s) in self.hashmap.iter() {
+            write!(&mut data, "{}`{}`{}`{}`{}`{}\n", question, s.asked, s.answered, s.answer_time, s.hints,
+                   s.skipped).unwrap();
+        }
+
+        match save_data(QUESTION_STATS_PATH, &data.into_bytes()) {
+            Ok(_) => (),
+            Err(e) => println!("save_data failed: {}", e),
+        }
+    }
+
+    pub fn load(&mut self) {
+        let fp = match open_file(QUESTION_STATS_PATH, true) {
+            Some(fp) => fp,
+            None     => return,
+        };
+
+        let mut data = String::new();
+        let mut reader = BufReader::new(&fp);
+
+        match reader.read_to_string(&mut data) {
+            Ok(_)  => (),
+            Err(e) => return println!("Failed to read question stats: {}", e),
+        };
+
+        for line in data.lines() {
+            let fields: Vec<&str> = line.split('`').collect();
+
+            if fields.len() != 6 {
+                continue;
+            }
+
+            let stats = QuestionStats {
+                asked: fields[1].parse().unwrap_or(0),
+                answered: fields[2].parse().unwrap_or(0),
+                answer_time: fields[3].parse().unwrap_or(0),
+                hints: fields[4].parse().unwrap_or(0),
+                skipped: fields[5].parse().unwrap_or(0),
+            };
+
+            self.hashmap.insert(fields[0].to_string(), stats);
+        }
+
+        println!("Loaded stats for {} questions", self.hashmap.len());
+    }
+}
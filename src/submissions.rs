@@ -0,0 +1,147 @@
+/*  submissions.rs
+ *
+ *
+ *  Copyright (C) 2015 rustybot All Rights Reserved.
+ *
+ *  This file is part of rustybot.
+ *
+ *  rustybot is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  rustybot is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with rustybot. If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::fmt::Write;
+use util::*;
+
+const SUBMISSIONS_PATH: &'static str = "data/submissions";
+
+// Maximum number of pending submissions per player
+const MAX_PENDING_PER_SUBMITTER: usize = 10;
+
+pub struct Submission {
+    pub id:           u32,
+    pub line:         String,   // The question as it will appear in the questions file
+    pub submitter_pk: String,
+    pub submitter:    String,   // Name of the submitter at the time of submission
+}
+
+/* Questions sent in by players, waiting for an admin to approve or reject them */
+pub struct SubmissionQueue {
+    submissions: Vec<Submission>,
+    next_id:     u32,
+}
+
+impl SubmissionQueue {
+    pub fn new() -> SubmissionQueue {
+        SubmissionQueue {
+            submissions: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    pub fn list(&self) -> &Vec<Submission> {
+        &self.submissions
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Submission> {
+        self.submissions.iter().find(|s| s.id == id)
+    }
+
+    /* Adds a question line to the queue and returns its id */
+    pub fn add(&mut self, line: &str, submitter_pk: &str, submitter: &str) -> Result<u32, String> {
+        if self.submissions.iter().any(|s| s.line == line) {
+            return Err("That question has already been submitted.".to_string());
+        }
+
+        if self.submissions.iter().filter(|s| s.submitter_pk == submitter_pk).count() >= MAX_PENDING_PER_SUBMITTER {
+            return Err("You have too many submissions waiting for review.".to_string());
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.submissions.push(Submission {
+            id: id,
+            line: line.to_string(),
+            submitter_pk: submitter_pk.to_string(),
+            submitter: submitter.replace('`', "'").replace('\n', " "),
+        });
+
+        self.save();
+        Ok(id)
+    }
+
+    /* Removes submission id from the queue and returns it */
+    pub fn take(&mut self, id: u32) -> Option<Submission> {
+        let idx = self.submissions.iter().position(|s| s.id == id)?;
+        let submission = self.submissions.remove(idx);
+        self.save();
+        Some(submission)
+    }
+
+    /* Saves submissions as one line each: id`submitter_pk`submitter`question line */
+    pub fn save(&self) {
+        let mut data = String::new();
+
+        for s in &self.submissions {
+            write!(&mut data, "{}`{}`{}`{}\n", s.id, s.submitter_pk, s.submitter, s.line).unwrap();
+        }
+
+        match save_data(SUBMISSIONS_PATH, &data.into_bytes()) {
+            Ok(_) => (),
+            Err(e) => println!("save_data failed: {}", e),
+        }
+    }
+
+    pub fn load(&mut self) {
+        let fp = match open_file(SUBMISSIONS_PATH, true) {
+            Some(fp) => fp,
+            None     => return,
+        };
+
+        let mut data = String::new();
+        let mut reader = BufReader::new(&fp);
+
+        match reader.read_to_string(&mut data) {
+            Ok(_)  => (),
+            Err(e) => return println!("Failed to read submissions: {}", e),
+        };
+
+        for line in data.lines() {
+            // The question line itself contains backticks so it takes up the rest of the line
+            let fields: Vec<&str> = line.splitn(4, '`').collect();
+
+            if fields.len() != 4 {
+                continue;
+            }
+
+            let id = match fields[0].parse::<u32>() {
+                Ok(id) => id,
+                Err(_) => continue,
+            };
+
+            self.next_id = self.next_id.max(id + 1);
+
+            self.submissions.push(Submission {
+                id: id,
+                submitter_pk: fields[1].to_string(),
+                submitter: fields[2].to_string(),
+                line: fields[3].to_string(),
+            });
+        }
+
+        println!("Loaded {} question submissions", self.submissions.len());
+    }
+}
@@ -0,0 +1,119 @@
+/*  suddendeath.rs
+ *
+ *
+ *  Copyright (C) 2015 rustybot All Rights Reserved.
+ *
+ *  This file is part of rustybot.
+ *
+ *  rustybot is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  rustybot is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with rustybot. If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use std::fmt::Write;
+use game::{GameMode, GameContext};
+use group::{Peer, get_peer_index};
+use trivia::{Trivia, TriviaQuestions};
+
+// Questions asked before giving up and letting the tied players share the win
+const SUDDEN_DEATH_QUESTIONS: u32 = 5;
+
+/*
+ * Trivia questions where only the tied players may answer, and the first of them to score
+ * wins. Started by the bot to break ties rather than with a command. The contestants are
+ * scored separately so the group's round scores are left alone until the tie is settled.
+ */
+pub struct SuddenDeath {
+    trivia:      Trivia,
+    contestants: Vec<Peer>,   // The tied players
+    winner:      Option<String>,
+}
+
+impl SuddenDeath {
+    pub fn new(contestants: Vec<Peer>) -> SuddenDeath {
+        SuddenDeath {
+            trivia: Trivia::new("sudden death", "Sudden death!", Box::new(TriviaQuestions::all())),
+            contestants: contestants,
+            winner: None,
+        }
+    }
+
+    /* The first contestant to score wins */
+    fn check_winner(&mut self) {
+        if self.winner.is_none() {
+            self.winner = self.contestants.iter().find(|p| p.round_score > 0).map(|p| p.public_key.to_string());
+        }
+    }
+}
+
+impl GameMode for SuddenDeath {
+    fn name(&self) -> &str {
+        self.trivia.name
+    }
+
+    fn owner_key(&self) -> &str {
+        ""
+    }
+
+    fn start(&mut self, ctx: &mut GameContext, owner_key: &str, _args: &str) -> bool {
+        if !self.trivia.start(ctx, owner_key, "") {
+            return false;
+        }
+
+        self.trivia.max_rounds = SUDDEN_DEATH_QUESTIONS;
+
+        let mut message = String::new();
+        write!(&mut message, "Only the tied players may answer, and the first to answer correctly wins. If nobody does \
+                              within {} questions they share the win.", SUDDEN_DEATH_QUESTIONS).unwrap();
+        ctx.send_message(&message);
+        true
+    }
+
+    fn tick(&mut self, ctx: &mut GameContext, _peers: &mut Vec<Peer>) -> bool {
+        if self.winner.is_some() {
+            return true;
+        }
+
+        let game_over = self.trivia.tick(ctx, &mut self.contestants);
+        self.check_winner();
+        game_over || self.winner.is_some()
+    }
+
+    fn on_message(&mut self, ctx: &mut GameContext, _peers: &mut Vec<Peer>, public_key: &str, message: &str) {
+        if self.winner.is_some() || get_peer_index(&self.contestants, public_key).is_none() {
+            return;
+        }
+
+        self.trivia.on_message(ctx, &mut self.contestants, public_key, message);
+        self.check_winner();
+    }
+
+    fn on_end(&mut self, ctx: &mut GameContext, _peers: &mut Vec<Peer>) {
+        self.trivia.on_end(ctx, &mut self.contestants);
+    }
+
+    fn hint(&mut self) -> String {
+        self.trivia.hint()
+    }
+
+    fn last_question(&self) -> Option<String> {
+        self.trivia.last_question()
+    }
+
+    fn tiebreak_winners(&self) -> Option<Vec<String>> {
+        match self.winner {
+            Some(ref pk) => Some(vec![pk.to_string()]),
+            None         => Some(self.contestants.iter().map(|p| p.public_key.to_string()).collect()),
+        }
+    }
+}
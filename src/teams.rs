@@ -0,0 +1,124 @@
+/*  teams.rs
+ *
+ *
+ *  Copyright (C) 2015 rustybot All Rights Reserved.
+ *
+ *  This file is part of rustybot.
+ *
+ *  rustybot is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  rustybot is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with rustybot. If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use group::Peer;
+
+// Longest team name allowed
+const MAX_TEAM_NAME_LENGTH: usize = 24;
+
+pub struct Team {
+    pub name:    String,
+    pub members: Vec<String>,   // Public keys of the members
+}
+
+/* The teams in a group. A peer may be on one team at a time, and empty teams are removed. */
+pub struct Teams {
+    teams: Vec<Team>,
+}
+
+impl Teams {
+    pub fn new() -> Teams {
+        Teams { teams: Vec::new() }
+    }
+
+    pub fn list(&self) -> &Vec<Team> {
+        &self.teams
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.teams.is_empty()
+    }
+
+    /* Team names are compared case-insensitively */
+    fn find(&self, name: &str) -> Option<usize> {
+        let name = name.to_lowercase();
+        self.teams.iter().position(|t| t.name.to_lowercase() == name)
+    }
+
+    /* Creates a team and puts public_key on it, taking them off any team they were on */
+    pub fn create(&mut self, name: &str, public_key: &str) -> Result<(), String> {
+        let name = name.trim();
+
+        if name.is_empty() || name.chars().count() > MAX_TEAM_NAME_LENGTH {
+            return Err(format!("Team names must be 1 to {} characters long.", MAX_TEAM_NAME_LENGTH));
+        }
+
+        // Backticks would break the team stats file format
+        if name.contains('`') {
+            return Err("Team names can't contain the ` character.".to_string());
+        }
+
+        if self.find(name).is_some() {
+            return Err("A team with that name already exists.".to_string());
+        }
+
+        self.leave(public_key);
+        self.teams.push(Team { name: name.to_string(), members: vec![public_key.to_string()] });
+        Ok(())
+    }
+
+    /* Puts public_key on the named team, taking them off any team they were on */
+    pub fn join(&mut self, name: &str, public_key: &str) -> Result<&str, String> {
+        if self.find(name).is_none() {
+            return Err("There's no team with that name.".to_string());
+        }
+
+        self.leave(public_key);
+
+        // Leaving may have removed an empty team and shifted the others
+        let idx = match self.find(name) {
+            Some(idx) => idx,
+            None      => return Err("There's no team with that name.".to_string()),
+        };
+
+        self.teams[idx].members.push(public_key.to_string());
+        Ok(&self.teams[idx].name)
+    }
+
+    /* Takes public_key off their team. Returns the team's name if they were on one. */
+    pub fn leave(&mut self, public_key: &str) -> Option<String> {
+        let idx = self.teams.iter().position(|t| t.members.iter().any(|m| m == public_key))?;
+        let name = self.teams[idx].name.to_string();
+
+        self.teams[idx].members.retain(|m| m != public_key);
+
+        if self.teams[idx].members.is_empty() {
+            self.teams.remove(idx);
+        }
+
+        Some(name)
+    }
+
+    /* Returns each team's total round score from the members in peers, highest first */
+    pub fn scores(&self, peers: &Vec<Peer>) -> Vec<(&str, u64)> {
+        let mut scores: Vec<(&str, u64)> = self.teams.iter().map(|t| {
+            let points = peers.iter()
+                              .filter(|p| t.members.iter().any(|m| *m == p.public_key))
+                              .map(|p| p.round_score)
+                              .sum();
+            (t.name.as_str(), points)
+        }).collect();
+
+        scores.sort_by(|a, b| b.1.cmp(&a.1));
+        scores
+    }
+}
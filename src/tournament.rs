@@ -0,0 +1,202 @@
+/*  tournament.rs
+ *
+ *
+ *  Copyright (C) 2015 rustybot All Rights Reserved.
+ *
+ *  This file is part of rustybot.
+ *
+ *  rustybot is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  rustybot is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with rustybot. If not, see <http://www.gnu.org/licenses/>.
+ *
+ */
+
+use std::fmt::Write;
+use group::Peer;
+
+// Fewest players a tournament can be started with
+const MIN_TOURNAMENT_PLAYERS: usize = 2;
+
+// Most games a tournament can be set to last
+pub const MAX_TOURNAMENT_GAMES: u32 = 20;
+
+// Number of games a tournament lasts if the organizer doesn't say
+pub const DEFAULT_TOURNAMENT_GAMES: u32 = 3;
+
+pub struct Entrant {
+    pub public_key: String,
+    pub nick:       String,
+    pub points:     u64,   // Points scored across all tournament games
+    pub games_won:  u32,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TournamentStage {
+    Registration,   // Players may sign up
+    Running,        // Games are counted towards the standings
+    Tiebreak,       // The leaders are tied after the last game and a sudden death question decides it
+}
+
+/*
+ * A fixed number of games whose scores add up to overall standings. Games in any group count
+ * as long as a registered player scores in them, and only registered players' points are kept.
+ */
+pub struct Tournament {
+    pub organizer:    String,   // Public key of the peer who created the tournament
+    pub games:        u32,      // Number of games to play
+    pub games_played: u32,
+    pub entrants:     Vec<Entrant>,
+    pub stage:        TournamentStage,
+    pub tiebreak_host:   Option<u32>,        // The group the sudden death tiebreak is played in
+    pub tiebreak_groups: Option<Vec<u32>>,   // Other groups with tied players, which follow the tiebreak. None until they're found.
+}
+
+impl Tournament {
+    pub fn new(organizer: &str, games: u32) -> Tournament {
+        Tournament {
+            organizer: organizer.to_string(),
+            games: games,
+            games_played: 0,
+            entrants: Vec::new(),
+            stage: TournamentStage::Registration,
+            tiebreak_host: None,
+            tiebreak_groups: None,
+        }
+    }
+
+    pub fn is_registered(&self, public_key: &str) -> bool {
+        self.entrants.iter().any(|e| e.public_key == public_key)
+    }
+
+    pub fn register(&mut self, public_key: &str, nick: &str) -> Result<(), String> {
+        if self.stage != TournamentStage::Registration {
+            return Err("Registration for the tournament is closed.".to_string());
+        }
+
+        if self.is_registered(public_key) {
+            return Err("You're already registered for the tournament.".to_string());
+        }
+
+        self.entrants.push(Entrant {
+            public_key: public_key.to_string(),
+            nick: nick.to_string(),
+            points: 0,
+            games_won: 0,
+        });
+
+        Ok(())
+    }
+
+    pub fn unregister(&mut self, public_key: &str) -> Result<(), String> {
+        if self.stage != TournamentStage::Registration {
+            return Err("You can't leave a tournament once it has started.".to_string());
+        }
+
+        if !self.is_registered(public_key) {
+            return Err("You're not registered for the tournament.".to_string());
+        }
+
+        self.entrants.retain(|e| e.public_key != public_key);
+        Ok(())
+    }
+
+    /* Closes registration and starts counting games */
+    pub fn start(&mut self) -> Result<(), String> {
+        if self.stage != TournamentStage::Registration {
+            return Err("The tournament has already started.".to_string());
+        }
+
+        if self.entrants.len() < MIN_TOURNAMENT_PLAYERS {
+            return Err(format!("A tournament needs at least {} registered players.", MIN_TOURNAMENT_PLAYERS));
+        }
+
+        self.stage = TournamentStage::Running;
+        Ok(())
+    }
+
+    /*
+     * Adds the round scores of the registered players in peers to the standings. Returns false if
+     * the game doesn't count because no registered player scored in it.
+     */
+    pub fn record_game(&mut self, peers: &Vec<Peer>, winners: &[String]) -> bool {
+        if self.stage != TournamentStage::Running || self.is_over() {
+            return false;
+        }
+
+        if !peers.iter().any(|p| p.round_score > 0 && self.is_registered(&p.public_key)) {
+            return false;
+        }
+
+        for p in peers {
+            let entrant = match self.entrants.iter_mut().find(|e| e.public_key == p.public_key) {
+                Some(entrant) => entrant,
+                None          => continue,
+            };
+
+            entrant.nick = p.get_nick();
+            entrant.points += p.round_score;
+
+            if winners.contains(&p.public_key) {
+                entrant.games_won += 1;
+            }
+        }
+
+        self.games_played += 1;
+        true
+    }
+
+    /* Returns true once all of the tournament's games have been played */
+    pub fn is_over(&self) -> bool {
+        self.games_played >= self.games
+    }
+
+    /* Returns the players with the most points */
+    pub fn leaders(&self) -> Vec<&Entrant> {
+        let best = self.entrants.iter().map(|e| e.points).max().unwrap_or(0);
+        self.entrants.iter().filter(|e| e.points == best).collect()
+    }
+
+    /* Returns the group hosting the tiebreak if groupnumber is one of the other groups following it */
+    pub fn tiebreak_host_for(&self, groupnumber: u32) -> Option<u32> {
+        match self.tiebreak_groups {
+            Some(ref groups) if groups.contains(&groupnumber) => self.tiebreak_host,
+            _ => None,
+        }
+    }
+
+    pub fn get_nick(&self, public_key: &str) -> Option<&str> {
+        self.entrants.iter().find(|e| e.public_key == public_key).map(|e| e.nick.as_str())
+    }
+
+    /* Returns the current standings, most points first */
+    pub fn standings(&self) -> String {
+        let mut entrants: Vec<&Entrant> = self.entrants.iter().collect();
+        entrants.sort_by(|a, b| b.points.cmp(&a.points));
+
+        let mut message = String::new();
+
+        match self.stage {
+            TournamentStage::Registration => write!(&mut message, "Tournament of {} games, registration open. \
+                                                                    Registered players:", self.games).unwrap(),
+            _ => write!(&mut message, "Tournament standings after game {} of {}:", self.games_played, self.games).unwrap(),
+        }
+
+        for (i, e) in entrants.iter().enumerate() {
+            match self.stage {
+                TournamentStage::Registration => write!(&mut message, "\n{}", e.nick).unwrap(),
+                _ => write!(&mut message, "\n{}. {}: {} points ({} games won)", i + 1, e.nick, e.points, e.games_won).unwrap(),
+            }
+        }
+
+        message
+    }
+}
@@ -23,15 +23,19 @@
 use rand::seq::SliceRandom;
 use rand::{Rng, thread_rng};
 use time::{get_time, Timespec, Duration};
-use bot::Bot;
 use std::fmt::Write;
-use group::{get_group_index, get_peer_index, get_peer_public_key};
+use game::{GameMode, GameContext};
+use group::{Peer, get_peer_index};
+use questions::{Question, QuestionBank, AnswerKind, check_answer, check_part, answer_parts, format_choices, get_choice};
+use numbers::{parse_quantity, parse_number};
+use math::{MATH_CATEGORY, MathSettings, generate_problem, default_math_level, math_level};
+use stats::Difficulty;
 use util::*;
 
 const PUNCTUATION: &'static str = " .,':;<>/\\=-()*&^%$#@![]{}|~?\"";
 
 // Number of seconds before the answer is given
-const QUESTION_TIME_LIMIT: i64 = 30;
+pub const QUESTION_TIME_LIMIT: i64 = 30;
 
 // Minimum number of points to win in a round
 const BASE_POINTS: i64 = 30;
@@ -39,39 +43,322 @@ const BASE_POINTS: i64 = 30;
 // Points multiplier for time bonus
 const BONUS_POINTS_MULTIPLIER: i64 = 4;
 
+// Game option that has players send their answers to the bot in a private message
+pub const PRIVATE_OPTION: &'static str = "private";
+
+// Game option that holds every player's first answer until the end of the round. Implies private answers.
+pub const SEALED_OPTION: &'static str = "sealed";
+
+// Game option that makes players buzz in with !buzz before answering
+pub const BUZZER_OPTION: &'static str = "buzzer";
+
+// Seconds a player has to answer after buzzing in
+const BUZZ_TIME_LIMIT: i64 = 10;
+
+// Points lost for a wrong answer or running out of time after buzzing in
+const BUZZ_PENALTY: u64 = 30;
+
+// Game option that ends the game with a final round where players wager their points
+pub const WAGER_OPTION: &'static str = "wager";
+
+// Seconds players have to send their wagers before the final question
+const WAGER_TIME_LIMIT: i64 = 30;
+
+// Game option that keeps asking questions until the game is stopped, adding points to the database every round
+pub const ENDLESS_OPTION: &'static str = "endless";
+
+// Unanswered rounds in a row after which an endless game pauses until someone chats
+const ENDLESS_IDLE_ROUNDS: u32 = 5;
+
+// In sealed rounds each correct answer after the fastest is worth this much less of the full points
+const SEALED_RANK_STEP: f64 = 0.2;
+
+// The smallest share of the full points a correct sealed answer earns
+const SEALED_MIN_SHARE: f64 = 0.2;
+
+// Points added for a perfectly accurate estimate, shrinking the further off the closest guess is
+const ESTIMATE_BONUS_POINTS: f64 = 120.0;
+
 // Max number of rounds
-pub const MAX_ROUNDS: u32 = 30;
+const MAX_ROUNDS: u32 = 30;
 
 // Seconds to wait between rounds
 const ROUND_DELAY: i64 = 3;
 
+// Number of random questions considered when looking for one of a particular difficulty
+const DIFFICULTY_CANDIDATES: usize = 20;
+
+// Points are multiplied by this plus the question's difficulty, so a question of unknown (0.5) difficulty is worth 1x
+const DIFFICULTY_POINTS_OFFSET: f64 = 0.5;
+
+/* Supplies the question and answer for each round of a question and answer game */
+pub trait QuestionSource {
+    /* Applies the arguments the game was started with. Returns an error message if they're invalid. */
+    fn configure(&mut self, _ctx: &GameContext, args: &str) -> Result<(), String> {
+        if !args.is_empty() {
+            return Err("This game takes no options.".to_string());
+        }
+
+        Ok(())
+    }
+
+    /* Returns true if there's nothing to ask */
+    fn is_empty(&self, ctx: &GameContext) -> bool;
+
+    fn next_question(&mut self, ctx: &GameContext) -> Option<Question>;
+}
+
+/*
+ * Parses the options for a game of trivia: a comma separated list of categories and/or a
+ * difficulty, e.g. "history,science hard".
+ */
+pub fn parse_trivia_options(questions: &QuestionBank, args: &str) -> Result<(Vec<String>, Option<Difficulty>), String>
+{
+    let mut difficulty = None;
+    let mut categories = Vec::new();
+
+    for word in args.split_whitespace() {
+        match Difficulty::from_name(&word.to_lowercase()) {
+            Some(d) => difficulty = Some(d),
+            None    => categories.push(word),
+        };
+    }
+
+    Ok((parse_categories(questions, &categories.join(","))?, difficulty))
+}
+
+/*
+ * Parses a comma separated list of categories. Math problems are generated rather than read
+ * from the question file, so math is always available as a category.
+ */
+pub fn parse_categories(questions: &QuestionBank, args: &str) -> Result<Vec<String>, String>
+{
+    let mut categories = Vec::new();
+
+    for name in args.split(',') {
+        let name = name.trim().to_lowercase();
+
+        if name.is_empty() || categories.contains(&name) {
+            continue;
+        }
+
+        if name != MATH_CATEGORY && !questions.has_category(&name) {
+            return Err(format!("Unknown category: {}. Type !categories for a list.", name));
+        }
+
+        categories.push(name);
+    }
+
+    Ok(categories)
+}
+
+/* Picks questions at random from the trivia question file, optionally restricted to some categories */
+pub struct TriviaQuestions {
+    categories: Vec<String>,           // Categories to pick from (empty for all)
+    difficulty: Option<Difficulty>,    // Preferred question difficulty (None for any)
+}
+
+impl TriviaQuestions {
+    /* Questions from every category, of any difficulty */
+    pub fn all() -> TriviaQuestions {
+        TriviaQuestions { categories: Vec::new(), difficulty: None }
+    }
+
+    fn has_math(&self) -> bool {
+        self.categories.iter().any(|c| c == MATH_CATEGORY)
+    }
+}
+
+impl QuestionSource for TriviaQuestions {
+    fn configure(&mut self, ctx: &GameContext, args: &str) -> Result<(), String> {
+        let (categories, difficulty) = parse_trivia_options(ctx.questions, args)?;
+        self.categories = categories;
+        self.difficulty = difficulty;
+        Ok(())
+    }
+
+    fn is_empty(&self, ctx: &GameContext) -> bool {
+        !self.has_math() && ctx.questions.count(&self.categories) == 0
+    }
+
+    fn next_question(&mut self, ctx: &GameContext) -> Option<Question> {
+        // Math gets an equal share of rounds with the other chosen categories
+        if self.has_math() {
+            let mut rng = thread_rng();
+
+            if ctx.questions.count(&self.categories) == 0 || rng.gen_range(0, self.categories.len()) == 0 {
+                let level = match self.difficulty {
+                    Some(d) => math_level(d.name()).unwrap_or(default_math_level()),
+                    None    => default_math_level(),
+                };
+
+                return Some(generate_problem(&MathSettings::from_level(level)));
+            }
+        }
+
+        let target = match self.difficulty {
+            Some(d) => d,
+            None    => return ctx.questions.random(&self.categories),
+        };
+
+        // Take the question closest to the wanted difficulty out of a handful of random picks
+        let mut best: Option<Question> = None;
+        let mut best_distance = 1.0;
+
+        for _ in 0..DIFFICULTY_CANDIDATES {
+            let question = match ctx.questions.random(&self.categories) {
+                Some(q) => q,
+                None    => break,
+            };
+
+            let difficulty = ctx.qstats.difficulty(&question, QUESTION_TIME_LIMIT);
+
+            if Difficulty::from_value(difficulty) == target {
+                return Some(question);
+            }
+
+            let distance = (difficulty - target.value()).abs();
+
+            if best.is_none() || distance < best_distance {
+                best = Some(question);
+                best_distance = distance;
+            }
+        }
+
+        best
+    }
+}
+
+pub fn new_trivia() -> Box<dyn GameMode>
+{
+    Box::new(Trivia::new("trivia", "Trivia time!", Box::new(TriviaQuestions::all())))
+}
+
+/* A guess in an estimation round */
+pub struct Guess {
+    pub public_key: String,
+    pub value:      f64,
+    pub time:       u64,   // Milliseconds since the round began
+}
+
+/* Progress through the optional final wager round */
+#[derive(Clone, Copy, PartialEq)]
+pub enum FinalStage {
+    NotStarted,
+    Wagering,   // Waiting for the finalists to send their wagers
+    Question,   // The final question has been asked
+    Done,
+}
+
+/* The outcome of trying to start a new round */
+#[derive(Clone, Copy, PartialEq)]
+pub enum RoundStart {
+    Started,
+    Delayed,       // The delay between rounds isn't over yet
+    Reloading,     // The questions file changed and can't be read until it's reloaded
+    NoQuestions,
+}
+
+/* A finalist's wager. Finalists who don't send one wager nothing. */
+pub struct Wager {
+    pub public_key: String,
+    pub amount:     Option<u64>,
+}
+
+/* An answer held until the end of a sealed round */
+pub struct SealedAnswer {
+    pub public_key: String,
+    pub answer:     String,
+    pub time:       u64,   // Milliseconds since the round began
+}
+
+/*
+ * A game of rounds where the first peer to type the correct answer wins the round.
+ * Trivia questions, word scrambles and so on only differ in their QuestionSource.
+ */
 pub struct Trivia {
-    pub question:    String,      // Current round's question
-    pub answer:      String,      // Current round's answer
-    pub running:     bool,        // True if a game is currently going
-    pub rounds:      u32,         // Current round number
-    pub hints:       Vec<String>, // Colleciton of current round's hints
-    pub hint_count:  usize,       // Number of hints given for the current round
-    pub round_timer: Timespec,    // Time since round began
-    pub end_timer:   Timespec,    // Time since last round ended
-    pub winner:      bool,        // True if the round has been won
-    pub disabled:    bool,        // True if trivia has been disabled
-    pub owner_key:   String,      // The owner of this particular round (enables !stop command)
+    pub name:        &'static str,             // Name of the game mode
+    pub greeting:    &'static str,             // Message sent when the game starts
+    pub source:      Box<dyn QuestionSource>,  // Supplies each round's question and answer
+    pub question:    String,                   // Current round's question
+    pub answer:      String,                   // Current round's answer
+    pub answer_kind: AnswerKind,               // How answers are compared for the current round
+    pub choices:     Vec<String>,              // Current round's options if it's multiple choice
+    pub locked_out:  Vec<String>,              // Public keys of peers who used up their guess this round
+    pub guesses:     Vec<Guess>,               // Guesses made this round if it's an estimate
+    pub parts:       Vec<String>,              // Parts of the current answer if it has several
+    pub found:       Vec<bool>,                // Which of the parts have been found
+    pub private:     bool,                     // True if answers are sent by private message
+    pub answered:    Vec<String>,              // Public keys of peers who've sent a private answer this round
+    pub sealed:      bool,                     // True if answers are held and graded at the end of each round
+    pub sealed_answers: Vec<SealedAnswer>,     // Each peer's first answer this round in a sealed game
+    pub buzzer:      bool,                     // True if players must buzz in before answering
+    pub buzzed:      Option<String>,           // Public key of the peer who has buzzed in and may answer
+    pub buzz_timer:  Timespec,                 // Time since the current buzz
+    pub wager:       bool,                     // True if the game ends with a final wager round
+    pub final_stage: FinalStage,
+    pub wagers:      Vec<Wager>,               // One for each peer who had points when the final round began
+    pub wager_timer: Timespec,                 // Time since wagering began
+    pub endless:     bool,                     // True if rounds go on until the game is stopped
+    pub paused:      bool,                     // True if an endless game is waiting for someone to chat
+    pub reloading:   bool,                     // True if the group was told we're waiting for the questions to reload
+    pub last_scored: u32,                      // The last round in which anyone scored
+    pub participants: Vec<String>,             // Public keys of peers who've answered or buzzed in this game
+    pub skip_votes:  Vec<String>,              // Public keys of peers who've voted to skip this round
+    pub tracked:     bool,                     // True if stats are recorded for the current question
+    pub difficulty:  f64,                      // Current question's difficulty between 0 and 1
+    pub last_question: String,                 // The most recent question with stats, for !report
+    pub rounds:      u32,                      // Current round number
+    pub max_rounds:  u32,                      // Number of rounds before the game ends
+    pub hints:       Vec<String>,              // Colleciton of current round's hints
+    pub hint_count:  usize,                    // Number of hints given for the current round
+    pub round_timer: Timespec,                 // Time since round began
+    pub end_timer:   Timespec,                 // Time since last round ended
+    pub winner:      bool,                     // True if the round has been won
+    pub owner_key:   String,                   // The owner of this particular round (enables !stop command)
 }
 
 impl Trivia {
-    pub fn new() -> Trivia {
+    pub fn new(name: &'static str, greeting: &'static str, source: Box<dyn QuestionSource>) -> Trivia {
         Trivia {
+            name: name,
+            greeting: greeting,
+            source: source,
             question: String::new(),
             answer: String::new(),
-            running: false,
+            answer_kind: AnswerKind::Text,
+            choices: Vec::new(),
+            locked_out: Vec::new(),
+            guesses: Vec::new(),
+            parts: Vec::new(),
+            found: Vec::new(),
+            private: false,
+            answered: Vec::new(),
+            sealed: false,
+            sealed_answers: Vec::new(),
+            buzzer: false,
+            buzzed: None,
+            buzz_timer: Timespec::new(0, 0),
+            wager: false,
+            final_stage: FinalStage::NotStarted,
+            wagers: Vec::new(),
+            wager_timer: Timespec::new(0, 0),
+            endless: false,
+            paused: false,
+            reloading: false,
+            last_scored: 0,
+            participants: Vec::new(),
+            skip_votes: Vec::new(),
+            tracked: false,
+            difficulty: 0.0,
+            last_question: String::new(),
             rounds: 0,
+            max_rounds: MAX_ROUNDS,
             hint_count: 0,
             hints: Vec::new(),
             round_timer: Timespec::new(0, 0),
             end_timer: Timespec::new(0, 0),
             winner: false,
-            disabled: false,
             owner_key: String::new(),
         }
     }
@@ -79,73 +366,999 @@ impl Trivia {
     pub fn reset(&mut self) {
         self.question.clear();
         self.answer.clear();
-        self.running = false;
+        self.choices.clear();
+        self.locked_out.clear();
+        self.guesses.clear();
+        self.parts.clear();
+        self.found.clear();
+        self.private = false;
+        self.answered.clear();
+        self.sealed = false;
+        self.sealed_answers.clear();
+        self.buzzer = false;
+        self.buzzed = None;
+        self.wager = false;
+        self.final_stage = FinalStage::NotStarted;
+        self.wagers.clear();
+        self.endless = false;
+        self.paused = false;
+        self.reloading = false;
+        self.last_scored = 0;
+        self.participants.clear();
+        self.skip_votes.clear();
         self.rounds = 0;
         self.hints.clear();
         self.hint_count = 0;
         self.round_timer = Timespec::new(0, 0);
         self.end_timer = Timespec::new(0, 0);
         self.winner = false;
+        self.last_question.clear();
         self.owner_key.clear();
     }
 
-    pub fn new_game(&mut self, owner_key: &str) {
-        self.running = true;
-        self.owner_key = owner_key.to_string();
+    /* Records the outcome of the current round in the question stats */
+    fn record_round(&self, ctx: &mut GameContext, answer_time: Option<u64>) {
+        if self.tracked {
+            ctx.qstats.record_round(&self.question, answer_time, self.hint_count as u32);
+        }
     }
 
-    /* Returns true if a new round is successfully set up */
-    pub fn new_round(&mut self, questions: &Vec<String>) -> bool {
+    /* Sets up a new round. A round is only used up once it has a question. */
+    pub fn new_round(&mut self, ctx: &GameContext) -> RoundStart {
         self.winner = false;
         self.question.clear();
         self.answer.clear();
+        self.choices.clear();
+        self.locked_out.clear();
+        self.guesses.clear();
+        self.parts.clear();
+        self.found.clear();
+        self.answered.clear();
+        self.sealed_answers.clear();
+        self.buzzed = None;
+        self.skip_votes.clear();
         self.hints.clear();
 
         if !timed_out(self.end_timer, ROUND_DELAY) {
-            return false;
+            return RoundStart::Delayed;
         }
 
-        let mut rng = thread_rng();
-        let idx = rng.gen_range(0, questions.len());
-        let split: Vec<&str> = questions[idx].split("`").collect();
+        let round = match self.source.next_question(ctx) {
+            Some(round) => round,
+            None if ctx.questions.is_stale() => return RoundStart::Reloading,
+            None => return RoundStart::NoQuestions,
+        };
 
         self.hint_count = 0;
         self.rounds += 1;
+        self.reloading = false;
+
+        self.difficulty = ctx.qstats.difficulty(&round, QUESTION_TIME_LIMIT);
+        self.question = round.question;
+        self.answer = round.answer;
+        self.answer_kind = round.kind;
+        self.choices = round.choices;
+        self.tracked = round.tracked;
+        self.round_timer = get_time();
+
+        if self.tracked {
+            self.last_question = self.question.to_string();
+        }
+
+        if self.has_parts() {
+            self.parts = answer_parts(&self.answer);
+            self.found = vec![false; self.parts.len()];
+            self.answer = self.parts.join(", ");
+        }
+
+        // Hints would give away multiple choice answers and estimates, and don't work for several parts
+        if self.answer_kind == AnswerKind::Estimate || self.has_parts() {
+            self.hints.clear();
+        } else if self.choices.is_empty() {
+            self.hints = generate_hints(&self.answer);
+        } else {
+            let mut rng = thread_rng();
+
+            // True/false is easier to read in its usual order
+            if self.choices.len() > 2 {
+                self.choices.shuffle(&mut rng);
+            }
+        }
+
+        RoundStart::Started
+    }
+
+    fn has_parts(&self) -> bool {
+        self.answer_kind == AnswerKind::Parts || self.answer_kind == AnswerKind::OrderedParts
+    }
+
+    /*
+     * Credits a peer for each part of a multi-part answer found in their message. Several parts may
+     * be given at once separated by commas. Ordered parts only count when they're the next one
+     * still missing. The round is won when every part has been found.
+     */
+    fn process_parts(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>, public_key: &str, message: &str) {
+        let mut new_parts = Vec::new();
+
+        for guess in message.split(|c| c == ',' || c == ';') {
+            let next = match self.found.iter().position(|f| !f) {
+                Some(next) => next,
+                None       => break,
+            };
+
+            let idx = if self.answer_kind == AnswerKind::OrderedParts {
+                if check_part(guess, &self.parts[next]) { Some(next) } else { None }
+            } else {
+                (0..self.parts.len()).find(|i| !self.found[*i] && check_part(guess, &self.parts[*i]))
+            };
+
+            if let Some(idx) = idx {
+                self.found[idx] = true;
+                new_parts.push(self.parts[idx].to_string());
+            }
+        }
 
-        if split.len() < 2 {
+        if new_parts.is_empty() {
+            return;
+        }
+
+        self.add_participant(public_key);
+
+        let peer_idx = match get_peer_index(peers, public_key) {
+            Some(idx) => idx,
+            None => return println!("get_peer_index() failed in process_parts() for public_key {:?}", public_key),
+        };
+
+        let points = (self.get_score() / self.parts.len() as u64).max(1) * new_parts.len() as u64;
+        let num_found = self.found.iter().filter(|f| **f).count();
+
+        // Whoever finds the last part wins the round
+        if num_found < self.parts.len() {
+            peers[peer_idx].add_points(points);
+        } else {
+            peers[peer_idx].update_round_score(points);
+        }
+
+        let mut message = String::new();
+        write!(&mut message, "{} found {} for {} points ({}/{} found)", peers[peer_idx].get_nick(),
+               new_parts.join(", "), points, num_found, self.parts.len()).unwrap();
+
+        if num_found < self.parts.len() {
+            return ctx.send_message(&message);
+        }
+
+        write!(&mut message, "\nAll parts found! The answer was: {}\n{} wins the round (Total: {} points, {} rounds)",
+               self.answer, peers[peer_idx].get_nick(), peers[peer_idx].get_round_score(),
+               peers[peer_idx].get_rounds_won()).unwrap();
+        ctx.send_message(&message);
+
+        let answer_time = (get_time() - self.round_timer).num_milliseconds();
+        self.record_round(ctx, Some(answer_time.max(0) as u64));
+
+        self.winner = true;
+        self.end_timer = get_time();
+        self.round_timer = Timespec::new(0, 0);
+    }
+
+    /* Estimates are collected from everyone, so they don't use the buzzer */
+    fn uses_buzzer(&self) -> bool {
+        self.buzzer && self.final_stage == FinalStage::NotStarted
+                    && (self.answer_kind != AnswerKind::Estimate || !self.choices.is_empty())
+    }
+
+    /* Takes points from the peer who buzzed in and locks them out for the rest of the question */
+    fn buzz_failed(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>, public_key: &str, reason: &str) {
+        self.buzzed = None;
+        self.locked_out.push(public_key.to_string());
+
+        let idx = match get_peer_index(peers, public_key) {
+            Some(idx) => idx,
+            None      => return,
+        };
+
+        peers[idx].remove_points(BUZZ_PENALTY);
+
+        let mut message = String::new();
+        write!(&mut message, "{}! {} loses {} points and is locked out of this question. The buzzer is open.",
+               reason, peers[idx].get_nick(), BUZZ_PENALTY).unwrap();
+        ctx.send_message(&message);
+    }
+
+    /*
+     * Estimates are already one guess each and settled at the end, so they don't need sealing.
+     * The final question of a wager game is always sealed.
+     */
+    fn is_sealed_round(&self) -> bool {
+        (self.sealed || self.final_stage == FinalStage::Question)
+            && (self.answer_kind != AnswerKind::Estimate || !self.choices.is_empty())
+    }
+
+    /* Returns true if answer is correct for the current round. Multi-part answers must contain every part. */
+    fn is_correct(&self, answer: &str) -> bool {
+        if !self.choices.is_empty() {
+            return match get_choice(&self.choices, answer) {
+                Some(choice) => choice.to_lowercase() == self.answer.to_lowercase(),
+                None         => false,
+            };
+        }
+
+        if !self.has_parts() {
+            return check_answer(answer, &self.answer, self.answer_kind);
+        }
+
+        let guesses: Vec<&str> = answer.split(|c| c == ',' || c == ';').collect();
+
+        if self.answer_kind == AnswerKind::OrderedParts {
+            return guesses.len() == self.parts.len() && self.parts.iter().zip(guesses).all(|(p, g)| check_part(g, p));
+        }
+
+        self.parts.iter().all(|p| guesses.iter().any(|g| check_part(g, p)))
+    }
+
+    /*
+     * Credits every correct answer in a sealed round. The fastest gets full points and the rest
+     * progressively less, and the fastest is the only one credited with a round win.
+     */
+    fn finish_sealed(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>) {
+        let mut correct: Vec<(String, u64)> = self.sealed_answers.iter()
+                                                   .filter(|a| self.is_correct(&a.answer))
+                                                   .map(|a| (a.public_key.to_string(), a.time))
+                                                   .collect();
+        correct.sort_by_key(|c| c.1);
+
+        let mut message = String::new();
+        write!(&mut message, "Time's up! The answer was: {}", self.answer).unwrap();
+
+        let full_points = match correct.first() {
+            Some(&(_, time)) => self.score_after(Duration::milliseconds(time as i64)),
+            None             => 0,
+        };
+
+        let mut results = Vec::new();
+
+        for (rank, &(ref public_key, _)) in correct.iter().enumerate() {
+            let idx = match get_peer_index(peers, public_key) {
+                Some(idx) => idx,
+                None      => continue,
+            };
+
+            let share = (1.0 - rank as f64 * SEALED_RANK_STEP).max(SEALED_MIN_SHARE);
+            let points = (full_points as f64 * share).round() as u64;
+
+            if results.is_empty() {
+                peers[idx].update_round_score(points);
+            } else {
+                peers[idx].add_points(points);
+            }
+
+            results.push(format!("{} ({})", peers[idx].get_nick(), points));
+        }
+
+        if results.is_empty() {
+            write!(&mut message, "\nNobody got it.").unwrap();
+        } else {
+            write!(&mut message, "\nCorrect: {}", results.join(", ")).unwrap();
+        }
+
+        ctx.send_message(&message);
+
+        let answer_time = correct.first().map(|c| c.1);
+        self.record_round(ctx, answer_time);
+    }
+
+    /*
+     * Gives the round to whoever guessed closest to the answer of an estimation round. Returns
+     * false if nobody still in the group made a guess.
+     */
+    fn finish_estimate(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>) -> bool {
+        let answer = match parse_quantity(&self.answer) {
+            Some((answer, _)) => answer,
+            None              => return false,
+        };
+
+        // Ties go to whoever guessed first
+        let best = self.guesses.iter()
+                               .filter(|g| g.value.is_finite() && get_peer_index(peers, &g.public_key).is_some())
+                               .min_by(|a, b| (a.value - answer).abs().total_cmp(&(b.value - answer).abs()));
+
+        let (public_key, value, time) = match best {
+            Some(g) => (g.public_key.to_string(), g.value, g.time),
+            None    => return false,
+        };
+
+        let peer_idx = get_peer_index(peers, &public_key).unwrap();
+        self.record_round(ctx, Some(time));
+
+        let points = self.get_estimate_score(value, answer);
+        peers[peer_idx].update_round_score(points);
+
+        let mut message = String::new();
+        write!(&mut message, "Time's up! The answer was: {}\n{} was closest with {} for {} points (Total: {} points, {} rounds)",
+               self.answer, peers[peer_idx].get_nick(), value, points, peers[peer_idx].get_round_score(),
+               peers[peer_idx].get_rounds_won()).unwrap();
+        ctx.send_message(&message);
+
+        self.winner = true;
+        true
+    }
+
+    /* Returns true if the game is over */
+    pub fn next_question(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>) -> bool {
+        if self.rounds > 0 && !self.winner && !self.answer.is_empty() {
+            if self.final_stage == FinalStage::Question {
+                self.finish_final(ctx, peers);
+            } else if self.is_sealed_round() {
+                self.finish_sealed(ctx, peers);
+            } else if self.answer_kind != AnswerKind::Estimate || !self.finish_estimate(ctx, peers) {
+                let mut message = String::new();
+                write!(&mut message, "Time's up! The answer was: {}", self.answer).unwrap();
+                ctx.send_message(&message);
+                self.record_round(ctx, None);
+            }
+
+            // The round is settled; clearing the answer keeps it from being settled again while we wait
+            self.answer.clear();
+            self.end_timer = get_time();
+        }
+
+        if self.endless && self.idle(ctx, peers) {
             return false;
         }
 
-        self.question = split[0].trim().to_string();
-        self.answer = split[1].trim().to_string();
-        self.round_timer = get_time();
-        self.hints = generate_hints(&self.answer);
+        if self.rounds >= self.max_rounds && !self.endless {
+            return self.do_final_round(ctx, peers);
+        }
+
+        match self.new_round(ctx) {
+            RoundStart::Started     => self.announce_round(ctx),
+            RoundStart::Delayed     => (),
+            RoundStart::Reloading   => self.wait_for_reload(ctx),
+            RoundStart::NoQuestions => {
+                ctx.send_message("There are no questions left to ask.");
+                return true;
+            },
+        };
+
+        false
+    }
+
+    /* Tells the group once that the next question waits for the changed questions file to be reloaded */
+    fn wait_for_reload(&mut self, ctx: &mut GameContext) {
+        if !self.reloading {
+            self.reloading = true;
+            ctx.send_message("The questions file has changed. The next question will be asked once it's reloaded.");
+        }
+    }
+
+    /*
+     * Counts public_key as an active player for skip votes. Only answers the game recognises count,
+     * since a wrong free text answer can't be told apart from chat.
+     */
+    fn add_participant(&mut self, public_key: &str) {
+        if !self.participants.iter().any(|p| p == public_key) {
+            self.participants.push(public_key.to_string());
+        }
+    }
+
+    /*
+     * Adds everyone's points to the database so an endless game never holds on to them. Returns
+     * true if anyone had points.
+     */
+    fn bank_scores(&self, ctx: &mut GameContext, peers: &mut Vec<Peer>) -> bool {
+        let mut banked = false;
+
+        for p in peers.iter_mut() {
+            if p.round_score == 0 && p.rounds_won == 0 {
+                continue;
+            }
+
+            ctx.db.update_score(&p.get_nick(), &p.public_key, p.round_score, p.rounds_won);
+            p.clear_round();
+            banked = true;
+        }
+
+        if banked {
+            ctx.db.save();
+        }
+
+        banked
+    }
 
+    /*
+     * Banks the scores of an endless game and pauses it once too many rounds in a row have gone
+     * unanswered. Returns true while it's paused.
+     */
+    fn idle(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>) -> bool {
+        if self.bank_scores(ctx, peers) {
+            self.last_scored = self.rounds;
+        }
+
+        if self.paused || self.rounds - self.last_scored < ENDLESS_IDLE_ROUNDS {
+            return self.paused;
+        }
+
+        self.paused = true;
+
+        let mut message = String::new();
+        write!(&mut message, "Nobody has answered for {} rounds, so {} is paused. Say anything or type !{} to carry on.",
+               ENDLESS_IDLE_ROUNDS, self.name, self.name).unwrap();
+        ctx.send_message(&message);
         true
     }
 
-    pub fn get_hint(&mut self) -> String {
-        if !self.running {
-            return "Cram it".to_string();
+    fn announce_round(&self, ctx: &mut GameContext) {
+        let mut message = String::new();
+
+        if self.final_stage == FinalStage::Question {
+            write!(&mut message, "FINAL ROUND: {}", self.question).unwrap();
+        } else {
+            write!(&mut message, "ROUND {}: {}", self.rounds, self.question).unwrap();
+        }
+
+        if !self.choices.is_empty() {
+            write!(&mut message, "\n{}", format_choices(&self.choices)).unwrap();
+        } else if self.answer_kind == AnswerKind::Estimate {
+            write!(&mut message, "\n(Estimate: one guess each, closest guess wins)").unwrap();
+        } else if self.answer_kind == AnswerKind::Parts {
+            write!(&mut message, "\n({} answers, in any order)", self.parts.len()).unwrap();
+        } else if self.answer_kind == AnswerKind::OrderedParts {
+            write!(&mut message, "\n({} answers, in order)", self.parts.len()).unwrap();
+        }
+
+        if self.uses_buzzer() {
+            write!(&mut message, "\n(Type !buzz to answer)").unwrap();
+        }
+
+        if self.final_stage == FinalStage::Question {
+            write!(&mut message, "\n(Finalists, send me your answer in a private message. Only your first answer counts.)").unwrap();
+        }
+
+        ctx.send_message(&message);
+    }
+
+    /*
+     * Runs the final wager round once the regular rounds are over: every peer with points may
+     * wager up to all of them, then a final question is asked. Returns true when the game is over.
+     */
+    fn do_final_round(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>) -> bool {
+        match self.final_stage {
+            FinalStage::NotStarted => {
+                if !self.wager {
+                    return true;
+                }
+
+                self.wagers = peers.iter()
+                                   .filter(|p| p.get_round_score() > 0)
+                                   .map(|p| Wager { public_key: p.public_key.to_string(), amount: None })
+                                   .collect();
+
+                if self.wagers.is_empty() {
+                    return true;
+                }
+
+                self.final_stage = FinalStage::Wagering;
+                self.wager_timer = get_time();
+
+                let mut message = String::new();
+                write!(&mut message, "FINAL ROUND! Everyone with points may wager up to all of them on the last question. \
+                                      Send me your wager in a private message within {} seconds.", WAGER_TIME_LIMIT).unwrap();
+                ctx.send_message(&message);
+                false
+            },
+            FinalStage::Wagering => {
+                let all_wagered = self.wagers.iter().all(|w| w.amount.is_some());
+
+                if !all_wagered && !timed_out(self.wager_timer, WAGER_TIME_LIMIT) {
+                    return false;
+                }
+
+                self.final_stage = FinalStage::Question;
+
+                match self.new_round(ctx) {
+                    RoundStart::Started     => self.announce_round(ctx),
+                    RoundStart::Delayed     => self.final_stage = FinalStage::Wagering,
+                    RoundStart::Reloading   => {
+                        self.final_stage = FinalStage::Wagering;
+                        self.wait_for_reload(ctx);
+                    },
+                    RoundStart::NoQuestions => {
+                        ctx.send_message("There are no questions left for the final round.");
+                        self.final_stage = FinalStage::Done;
+                        return true;
+                    },
+                };
+
+                false
+            },
+            FinalStage::Question => false,
+            FinalStage::Done     => true,
+        }
+    }
+
+    /* Handles a private message during wagering. Wagers may be changed until the final question is asked. */
+    fn place_wager(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>, public_key: &str, message: &str) {
+        let idx = match get_peer_index(peers, public_key) {
+            Some(idx) => idx,
+            None      => return,
+        };
+
+        let score = peers[idx].get_round_score();
+
+        let wager = match self.wagers.iter_mut().find(|w| w.public_key == public_key) {
+            Some(wager) => wager,
+            None        => return,
+        };
+
+        let mut response = String::new();
+
+        // Wagers and scores stay between the bot and the finalist; the group only hears that one was placed
+        match parse_number(message) {
+            Some(n) if n >= 0.0 && n.fract() == 0.0 && n as u64 <= score => {
+                let first = wager.amount.is_none();
+                wager.amount = Some(n as u64);
+
+                write!(&mut response, "Your wager of {} points is in. You can change it until the final question is asked.",
+                       n as u64).unwrap();
+                ctx.send_private_message(public_key, &response);
+
+                if first {
+                    let announcement = format!("{} has placed a wager", peers[idx].get_nick());
+                    ctx.send_message(&announcement);
+                }
+            },
+            _ => {
+                write!(&mut response, "Your wager must be a whole number from 0 to {}", score).unwrap();
+                ctx.send_private_message(public_key, &response);
+            },
+        }
+    }
+
+    /* Adds each finalist's wager to their score if they answered the final question correctly, or takes it away if not */
+    fn finish_final(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>) {
+        // For an estimate, the closest guesses count as correct
+        let target = parse_quantity(&self.answer).map(|(n, _)| n);
+        let best_distance = match target {
+            Some(target) if self.answer_kind == AnswerKind::Estimate => {
+                self.guesses.iter().map(|g| (g.value - target).abs()).fold(None, |best: Option<f64>, d| {
+                    Some(best.map_or(d, |b| b.min(d)))
+                })
+            },
+            _ => None,
+        };
+
+        let mut message = String::new();
+        write!(&mut message, "Time's up! The answer was: {}", self.answer).unwrap();
+
+        let mut first_correct = None;
+
+        for wager in &self.wagers {
+            let idx = match get_peer_index(peers, &wager.public_key) {
+                Some(idx) => idx,
+                None      => continue,
+            };
+
+            let correct = match (best_distance, target) {
+                (Some(best), Some(target)) => self.guesses.iter().any(|g| g.public_key == wager.public_key
+                                                                       && (g.value - target).abs() <= best),
+                _ => self.sealed_answers.iter().any(|a| a.public_key == wager.public_key && self.is_correct(&a.answer)),
+            };
+
+            let amount = wager.amount.unwrap_or(0);
+
+            if correct {
+                peers[idx].add_points(amount);
+
+                if first_correct.is_none() {
+                    first_correct = self.sealed_answers.iter().find(|a| a.public_key == wager.public_key).map(|a| a.time);
+                }
+            } else {
+                peers[idx].remove_points(amount);
+            }
+
+            write!(&mut message, "\n{} wagered {} and was {} ({}{})", peers[idx].get_nick(), amount,
+                   if correct { "right" } else { "wrong" }, if correct { "+" } else { "-" }, amount).unwrap();
+        }
+
+        ctx.send_message(&message);
+        self.record_round(ctx, first_correct);
+        self.final_stage = FinalStage::Done;
+    }
+
+    pub fn process_answer(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>, public_key: &str, message: &str) {
+        if self.winner {
+            return;
+        }
+
+        // Only the peer who buzzed in may answer, and they only get one try
+        if self.uses_buzzer() {
+            if self.buzzed.as_ref().map(|k| k.as_str()) != Some(public_key) {
+                return;
+            }
+
+            self.buzzed = None;
+
+            if !self.is_correct(message) {
+                return self.buzz_failed(ctx, peers, public_key, "Wrong");
+            }
+        }
+
+        if self.has_parts() && self.choices.is_empty() {
+            return self.process_parts(ctx, peers, public_key, message);
+        }
+
+        // Estimates are collected and settled when time runs out
+        if self.answer_kind == AnswerKind::Estimate && self.choices.is_empty() {
+            let value = match parse_quantity(message) {
+                Some((value, _)) => value,
+                None             => return,
+            };
+
+            if self.guesses.iter().any(|g| g.public_key == public_key) {
+                return;
+            }
+
+            let time = (get_time() - self.round_timer).num_milliseconds().max(0) as u64;
+            self.guesses.push(Guess { public_key: public_key.to_string(), value: value, time: time });
+            self.add_participant(public_key);
+            return;
+        }
+
+        if self.choices.is_empty() {
+            if !check_answer(message, &self.answer, self.answer_kind) {
+                return;
+            }
+
+            self.add_participant(public_key);
+        } else {
+            // Each peer gets one guess per multiple choice question
+            let choice = match get_choice(&self.choices, message) {
+                Some(choice) => choice.to_lowercase(),
+                None         => return,
+            };
+
+            if self.locked_out.iter().any(|k| k == public_key) {
+                return;
+            }
+
+            self.add_participant(public_key);
+
+            if choice != self.answer.to_lowercase() {
+                self.locked_out.push(public_key.to_string());
+                return;
+            }
         }
 
-        let hint = match self.hints.pop() {
-            Some(hint) => hint.to_string(),
-            None       => "No more hints".to_string(),
+        let peer_idx = match get_peer_index(peers, public_key) {
+            Some(idx) => idx,
+            None => return println!("get_peer_index() failed in process_answer() for public_key {:?}", public_key),
         };
 
-        hint
+        let answer_time = (get_time() - self.round_timer).num_milliseconds();
+        self.record_round(ctx, Some(answer_time.max(0) as u64));
+
+        let points = self.get_score();
+        peers[peer_idx].update_round_score(points);
+        let score = peers[peer_idx].get_round_score();
+        let rounds_won = peers[peer_idx].get_rounds_won();
+        let peername = peers[peer_idx].get_nick();
+
+        let mut response = String::new();
+        write!(&mut response, "{} got the answer for {} points (Total: {} points, {} rounds)",
+               peername, points, score, rounds_won).unwrap();
+
+        // The group hasn't seen the answer when it was sent privately
+        if self.private {
+            write!(&mut response, "\nThe answer was: {}", self.answer).unwrap();
+        }
+
+        ctx.send_message(&response);
+
+        self.winner = true;
+        self.end_timer = get_time();
+        self.round_timer = Timespec::new(0, 0);
     }
 
-    /* The score is based on how many seconds are left in the round and how many hints have been used */
+    /*
+     * The score is based on how many seconds are left in the round and how many hints have been used,
+     * weighted by how difficult the question has proven to be.
+     */
     fn get_score(&self) -> u64 {
-        let delta = Duration::seconds(QUESTION_TIME_LIMIT) - (get_time() - self.round_timer);
+        self.score_after(get_time() - self.round_timer)
+    }
+
+    /* Returns the score for an answer given elapsed time into the round */
+    fn score_after(&self, elapsed: Duration) -> u64 {
+        let delta = Duration::seconds(QUESTION_TIME_LIMIT) - elapsed;
         let t = Duration::num_seconds(&delta) + 1;
         let score = (t * BONUS_POINTS_MULTIPLIER / (self.hint_count as i64 + 1)) + BASE_POINTS;
-        score as u64
+        (score as f64 * (DIFFICULTY_POINTS_OFFSET + self.difficulty)).round() as u64
+    }
+
+    /* Estimates score by how close the guess was relative to the size of the answer */
+    fn get_estimate_score(&self, guess: f64, answer: f64) -> u64 {
+        let error = (guess - answer).abs() / answer.abs().max(1.0);
+        let score = BASE_POINTS as f64 + ESTIMATE_BONUS_POINTS * (1.0 - error.min(1.0));
+        (score * (DIFFICULTY_POINTS_OFFSET + self.difficulty)).round() as u64
     }
 }
 
+impl GameMode for Trivia {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn owner_key(&self) -> &str {
+        &self.owner_key
+    }
+
+    fn start(&mut self, ctx: &mut GameContext, owner_key: &str, args: &str) -> bool {
+        let (private, args) = take_option(args, PRIVATE_OPTION);
+        let (sealed, args) = take_option(&args, SEALED_OPTION);
+        let (buzzer, args) = take_option(&args, BUZZER_OPTION);
+        let (wager, args) = take_option(&args, WAGER_OPTION);
+        let (endless, args) = take_option(&args, ENDLESS_OPTION);
+
+        if endless && wager {
+            ctx.send_message("Endless games have no final round to wager on.");
+            return false;
+        }
+
+        if let Err(e) = self.source.configure(ctx, &args) {
+            ctx.send_message(&e);
+            return false;
+        }
+
+        if self.source.is_empty(ctx) {
+            let mut message = String::new();
+            write!(&mut message, "No questions have been loaded for {}.", self.name).unwrap();
+            ctx.send_message(&message);
+            return false;
+        }
+
+        self.reset();
+        self.owner_key = owner_key.to_string();
+        self.private = private || sealed;
+        self.sealed = sealed;
+        self.buzzer = buzzer && !sealed;
+        self.wager = wager;
+        self.endless = endless;
+        ctx.send_message(self.greeting);
+
+        if self.endless {
+            ctx.send_message("This game goes on until it's stopped, and points go straight to the leaderboard.");
+        }
+
+        if self.buzzer {
+            let mut message = String::new();
+            write!(&mut message, "Type !buzz to answer. You'll have {} seconds, and a wrong answer costs {} points.",
+                   BUZZ_TIME_LIMIT, BUZZ_PENALTY).unwrap();
+            ctx.send_message(&message);
+        }
+
+        if self.sealed {
+            ctx.send_message("Send me your answers in a private message. Only your first answer counts, and \
+                              everyone who gets it right scores when time runs out, faster answers scoring more.");
+        } else if self.private {
+            ctx.send_message("Send me your answers in a private message. The group only sees who answered.");
+        }
+
+        true
+    }
+
+    fn tick(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>) -> bool {
+        if let Some(public_key) = self.buzzed.clone() {
+            if timed_out(self.buzz_timer, BUZZ_TIME_LIMIT) {
+                self.buzz_failed(ctx, peers, &public_key, "Time's up");
+            }
+        }
+
+        if !timed_out(self.round_timer, QUESTION_TIME_LIMIT) {
+            return false;
+        }
+
+        self.next_question(ctx, peers)
+    }
+
+    fn on_message(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>, public_key: &str, message: &str) {
+        if self.paused {
+            self.resume(ctx);
+            return;
+        }
+
+        if !self.private && self.final_stage == FinalStage::NotStarted {
+            self.process_answer(ctx, peers, public_key, message);
+        }
+    }
+
+    fn buzz(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>, public_key: &str) {
+        if !self.buzzer {
+            return ctx.send_message("The buzzer is only used in buzzer games. Start one with buzzer, e.g. !trivia buzzer");
+        }
+
+        if self.answer.is_empty() || self.winner || !self.uses_buzzer() {
+            return;
+        }
+
+        let idx = match get_peer_index(peers, public_key) {
+            Some(idx) => idx,
+            None      => return,
+        };
+
+        self.add_participant(public_key);
+
+        let mut message = String::new();
+
+        if let Some(ref buzzed) = self.buzzed {
+            if let Some(buzzed_idx) = get_peer_index(peers, buzzed) {
+                write!(&mut message, "{} has the buzzer.", peers[buzzed_idx].get_nick()).unwrap();
+                return ctx.send_message(&message);
+            }
+        }
+
+        if self.locked_out.iter().any(|k| k == public_key) {
+            write!(&mut message, "{}, you're locked out of this question.", peers[idx].get_nick()).unwrap();
+            return ctx.send_message(&message);
+        }
+
+        self.buzzed = Some(public_key.to_string());
+        self.buzz_timer = get_time();
+
+        write!(&mut message, "{} buzzed in! You have {} seconds to answer.", peers[idx].get_nick(), BUZZ_TIME_LIMIT).unwrap();
+        ctx.send_message(&message);
+    }
+
+    fn private_round_start(&self, _public_key: &str) -> Option<Timespec> {
+        match self.final_stage {
+            FinalStage::Wagering => Some(self.wager_timer),
+            FinalStage::Question => Some(self.round_timer),
+            _ if self.private    => Some(self.round_timer),
+            _                    => None,
+        }
+    }
+
+    fn on_private_message(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>, public_key: &str,
+                          message: &str) -> bool {
+        match self.final_stage {
+            FinalStage::Wagering => {
+                self.place_wager(ctx, peers, public_key, message);
+                return true;
+            },
+            FinalStage::Question => {
+                if !self.wagers.iter().any(|w| w.public_key == public_key) {
+                    return true;
+                }
+            },
+            _ => {
+                if !self.private {
+                    return false;
+                }
+            },
+        }
+
+        self.add_participant(public_key);
+
+        if self.answer.is_empty() || self.winner {
+            return true;
+        }
+
+        if self.is_sealed_round() {
+            if self.sealed_answers.iter().any(|a| a.public_key == public_key) {
+                return true;
+            }
+
+            let time = (get_time() - self.round_timer).num_milliseconds().max(0) as u64;
+            self.sealed_answers.push(SealedAnswer { public_key: public_key.to_string(),
+                                                    answer: message.to_string(),
+                                                    time: time });
+        } else {
+            self.process_answer(ctx, peers, public_key, message);
+        }
+
+        // A correct answer is announced by process_answer; otherwise only say that the peer answered
+        if !self.winner && !self.answered.iter().any(|k| k == public_key) {
+            self.answered.push(public_key.to_string());
+
+            if let Some(idx) = get_peer_index(peers, public_key) {
+                let mut message = String::new();
+                write!(&mut message, "{} answered", peers[idx].get_nick()).unwrap();
+                ctx.send_message(&message);
+            }
+        }
+
+        true
+    }
+
+    fn on_end(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>) {
+        if self.endless {
+            self.bank_scores(ctx, peers);
+        }
+
+        self.reset();
+        ctx.qstats.save();
+    }
+
+    fn skip(&mut self, ctx: &mut GameContext, peers: &mut Vec<Peer>, public_key: &str, fraction: f64) {
+        if self.answer.is_empty() || self.winner || self.final_stage != FinalStage::NotStarted {
+            return;
+        }
+
+        if !self.participants.iter().any(|p| p == public_key) {
+            return ctx.send_message("Only players who have answered in this game can vote to skip.");
+        }
+
+        if self.skip_votes.iter().any(|p| p == public_key) {
+            return;
+        }
+
+        self.skip_votes.push(public_key.to_string());
+
+        let needed = ((self.participants.len() as f64 * fraction).ceil() as usize).max(1);
+        let mut message = String::new();
+
+        if self.skip_votes.len() < needed {
+            let nick = get_peer_index(peers, public_key).map(|i| peers[i].get_nick()).unwrap_or_default();
+            write!(&mut message, "{} voted to skip ({}/{} votes needed)", nick, self.skip_votes.len(), needed).unwrap();
+            return ctx.send_message(&message);
+        }
+
+        write!(&mut message, "Question skipped. The answer was: {}", self.answer).unwrap();
+        ctx.send_message(&message);
+
+        if self.tracked {
+            ctx.qstats.record_skip(&self.question, self.hint_count as u32);
+        }
+
+        // Settle the round now so the next question comes after the usual delay
+        self.answer.clear();
+        self.buzzed = None;
+        self.end_timer = get_time();
+        self.round_timer = Timespec::new(0, 0);
+    }
+
+    fn resume(&mut self, ctx: &mut GameContext) -> bool {
+        if !self.paused {
+            return false;
+        }
+
+        self.paused = false;
+        self.last_scored = self.rounds;
+        self.end_timer = get_time();
+
+        let mut message = String::new();
+        write!(&mut message, "Welcome back! {} resumes in {} seconds.", self.name, ROUND_DELAY).unwrap();
+        ctx.send_message(&message);
+        true
+    }
+
+    fn last_question(&self) -> Option<String> {
+        if self.last_question.is_empty() {
+            return None;
+        }
+
+        Some(self.last_question.to_string())
+    }
+
+    fn hint(&mut self) -> String {
+        match self.hints.pop() {
+            Some(hint) => {
+                self.hint_count += 1;
+                hint
+            },
+            None => "No more hints".to_string(),
+        }
+    }
+}
+
+/* Removes option from a game's arguments if it's there. Returns whether it was found and the remaining arguments. */
+pub fn take_option(args: &str, option: &str) -> (bool, String)
+{
+    let words: Vec<&str> = args.split_whitespace().collect();
+    let found = words.iter().any(|w| w.to_lowercase() == option);
+    let rest: Vec<&str> = words.into_iter().filter(|w| w.to_lowercase() != option).collect();
+
+    (found, rest.join(" "))
+}
+
 fn answer_is_year(answer: &str) -> bool
 {
     if answer.len() != 4 {
@@ -162,6 +1375,28 @@ fn answer_is_year(answer: &str) -> bool
     first_char == '1' || first_char == '2'
 }
 
+/* Returns true if ch is a space or punctuation character, which are never hidden from players */
+pub fn is_punctuation(ch: char) -> bool
+{
+    PUNCTUATION.contains(ch)
+}
+
+/* Returns answer with every character that isn't marked as revealed replaced with a dash */
+pub fn mask_answer(answer: &str, revealed: &Vec<bool>) -> String
+{
+    let mut masked = String::new();
+
+    for (i, ch) in answer.chars().enumerate() {
+        if revealed.get(i).cloned().unwrap_or(false) {
+            masked.push(ch);
+        } else {
+            masked.push('-');
+        }
+    }
+
+    masked
+}
+
 /* Creates a vector of hints for the current answer. Hints are ordered by least to most letters revealed. */
 fn generate_hints(answer: &str) -> Vec<String>
 {
@@ -188,9 +1423,7 @@ fn generate_hints(answer: &str) -> Vec<String>
 
     // Spaces and punctuation are freebies
     for (i, ch) in answer.chars().enumerate() {
-        let p = ch.to_string();
-
-        if PUNCTUATION.contains(&p) {
+        if is_punctuation(ch) {
             used[i] = true;
         } else {
             indices.push(i);
@@ -204,76 +1437,13 @@ fn generate_hints(answer: &str) -> Vec<String>
     let num_hints = ((len / 2) / chars_per_hint) + 1;
 
     for _ in 0..num_hints {
-        let mut hint = String::new();
-
         for _ in 0..chars_per_hint {
             let idx = indices.pop().unwrap_or(0);
             used[idx] = true;
         }
 
-        for (i, ch) in answer.chars().enumerate() {
-            hint = if used[i] { hint + &ch.to_string() } else { hint + "-" };
-        }
-
-        hints.push(hint);
+        hints.push(mask_answer(answer, &used));
     }
 
     hints.into_iter().rev().collect()
 }
-
-pub fn process_answer(bot: &mut Bot, groupnumber: u32, peernumber: u32, message: &str)
-{
-    let index = match get_group_index(bot, groupnumber) {
-        Some(index) => index,
-        None => return println!("get_group_index() failed in process_answer() for groupnumber {}", groupnumber),
-    };
-
-    if !bot.groups[index].trivia.running {
-        return;
-    }
-
-    if bot.groups[index].trivia.winner {
-        return;
-    }
-
-    if message.to_lowercase() != bot.groups[index].trivia.answer.to_lowercase() {
-        return;
-    }
-
-    let public_key = match get_peer_public_key(bot.tox, groupnumber, peernumber) {
-        Some(key) => key,
-        None => return println!("get_peer_public_key() failed in process_answer() for groupnumber {}, peernumber {}",
-                                     groupnumber, peernumber),
-    };
-
-    let peer_idx = match get_peer_index(&mut bot.groups[index].peers, &public_key) {
-        Some(idx) => idx,
-        None => return println!("get_peer_index() failed in process_answer() for public_key {:?}", public_key.to_string()),
-    };
-
-    let points = bot.groups[index].trivia.get_score();
-    bot.groups[index].peers[peer_idx].update_round_score(points);
-    let score = bot.groups[index].peers[peer_idx].get_round_score();
-    let rounds_won = bot.groups[index].peers[peer_idx].get_rounds_won();
-    let peername = bot.groups[index].peers[peer_idx].get_nick();
-
-    let mut response = String::new();
-    write!(&mut response, "{} got the answer for {} points (Total: {} points, {} rounds)",
-           peername, points, score, rounds_won).unwrap();
-    bot.groups[index].send_message(bot.tox, &response);
-
-    bot.groups[index].trivia.winner = true;
-    bot.groups[index].trivia.end_timer = get_time();
-    bot.groups[index].trivia.round_timer = Timespec::new(0, 0);
-}
-
-pub fn do_trivia(bot: &mut Bot)
-{
-    for group in &mut bot.groups {
-        if group.trivia.running {
-            if timed_out(group.trivia.round_timer, QUESTION_TIME_LIMIT) {
-                group.next_trivia_question(bot.tox, &bot.questions, &mut bot.db);
-            }
-        }
-    }
-}
@@ -64,7 +64,7 @@ pub fn save_data(path_name: &str, data: &Vec<u8>) -> Result<usize, String>
     let display = path.display();
     let mut options = OpenOptions::new();
 
-    let fp = options.write(true).create(true).open(&path)
+    let fp = options.write(true).create(true).truncate(true).open(&path)
                     .map_err(|e| format!("Couldn't open file {}: {}", display, e))?;
 
     let mut writer = BufWriter::new(&fp);